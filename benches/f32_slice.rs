@@ -0,0 +1,56 @@
+use binary_stream::{BinaryWriter, Endian, Options};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::{hint::black_box, io::Cursor};
+
+const LEN: usize = 64 * 1024;
+
+fn options(endian: Endian) -> Options {
+    Options {
+        endian,
+        ..Default::default()
+    }
+}
+
+fn write_f32_slice(c: &mut Criterion) {
+    let values: Vec<f32> = (0..LEN).map(|i| i as f32 * 0.5).collect();
+    let foreign = if Endian::native() == Endian::Little {
+        Endian::Big
+    } else {
+        Endian::Little
+    };
+
+    let mut group = c.benchmark_group("write_f32_slice");
+    group.throughput(Throughput::Bytes((LEN * 4) as u64));
+    group.bench_function("native", |b| {
+        let mut stream = Cursor::new(Vec::with_capacity(LEN * 4));
+        b.iter(|| {
+            stream.set_position(0);
+            let mut writer =
+                BinaryWriter::new(&mut stream, options(Endian::native()));
+            writer.write_f32_slice_native(black_box(&values)).unwrap()
+        })
+    });
+    group.bench_function("foreign", |b| {
+        let mut stream = Cursor::new(Vec::with_capacity(LEN * 4));
+        b.iter(|| {
+            stream.set_position(0);
+            let mut writer = BinaryWriter::new(&mut stream, options(foreign));
+            writer.write_f32_slice_native(black_box(&values)).unwrap()
+        })
+    });
+    group.bench_function("element_wise", |b| {
+        let mut stream = Cursor::new(Vec::with_capacity(LEN * 4));
+        b.iter(|| {
+            stream.set_position(0);
+            let mut writer =
+                BinaryWriter::new(&mut stream, options(Endian::native()));
+            for value in black_box(&values) {
+                writer.write_f32(value).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, write_f32_slice);
+criterion_main!(benches);
@@ -0,0 +1,284 @@
+//! Derive macros for the `Encodable` and `Decodable` traits
+//! in [binary-stream](https://docs.rs/binary-stream).
+//!
+//! Fields are encoded and decoded in declaration order; a field
+//! marked with `#[binary(skip)]` is not written and is set to the
+//! default value when decoding.
+//!
+//! A struct marked with `#[binary(option_bitmap)]` writes the
+//! presence of every `Option` field as one bit in a leading bitmap
+//! (the first field in the least significant bit of the first byte)
+//! followed by the fields with only the values that are present.
+#![deny(missing_docs)]
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Field, Fields,
+    GenericArgument, Generics, Index, Member, Path, PathArguments, Result,
+    Type,
+};
+
+/// Derive `Encodable` for a struct.
+#[proc_macro_derive(Encodable, attributes(binary))]
+pub fn derive_encodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encodable(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derive `Decodable` for a struct.
+#[proc_macro_derive(Decodable, attributes(binary))]
+pub fn derive_decodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decodable(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_encodable(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = struct_fields(&input)?;
+    let name = &input.ident;
+    let generics = add_bounds(
+        &input.generics,
+        &fields,
+        parse_quote!(::binary_stream::Encodable),
+        None,
+    );
+    let (impl_generics, ty_generics, where_clause) =
+        generics.split_for_impl();
+    let bitmap = option_bitmap(&input, &fields)?;
+    let bitmap_len = bitmap.len().div_ceil(8);
+    let presence = bitmap.iter().enumerate().map(|(bit, member)| {
+        let (index, mask) = (bit / 8, 1u8 << (bit % 8));
+        quote! {
+            if self.#member.is_some() {
+                bitmap[#index] |= #mask;
+            }
+        }
+    });
+    let presence = (!bitmap.is_empty()).then(|| {
+        quote! {
+            let mut bitmap = [0u8; #bitmap_len];
+            #(#presence)*
+            writer.write_bytes(bitmap)?;
+        }
+    });
+    let statements =
+        fields
+            .iter()
+            .filter(|(_, _, skip)| !skip)
+            .map(|(member, _, _)| {
+                if bitmap.contains(member) {
+                    quote! {
+                        if let Some(value) = &self.#member {
+                            value.encode(&mut *writer)?;
+                        }
+                    }
+                } else {
+                    quote!(self.#member.encode(&mut *writer)?;)
+                }
+            });
+    Ok(quote! {
+        impl #impl_generics ::binary_stream::Encodable for #name #ty_generics
+        #where_clause
+        {
+            fn encode<W: ::std::io::Write + ::std::io::Seek>(
+                &self,
+                writer: &mut ::binary_stream::BinaryWriter<W>,
+            ) -> ::std::io::Result<()> {
+                #presence
+                #(#statements)*
+                Ok(())
+            }
+        }
+    })
+}
+
+fn expand_decodable(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = struct_fields(&input)?;
+    let name = &input.ident;
+    let bitmap = option_bitmap(&input, &fields)?;
+    let mut generics = add_bounds(
+        &input.generics,
+        &fields,
+        parse_quote!(::binary_stream::Decodable),
+        Some(parse_quote!(::std::default::Default)),
+    );
+    if generics.type_params().next().is_some() {
+        // Present bitmap values are decoded into a default value
+        let where_clause = generics.make_where_clause();
+        for (_, field, _) in fields
+            .iter()
+            .filter(|(member, _, _)| bitmap.contains(member))
+        {
+            if let Some(inner) = option_inner(&field.ty) {
+                where_clause
+                    .predicates
+                    .push(parse_quote!(#inner: ::std::default::Default));
+            }
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) =
+        generics.split_for_impl();
+    let bitmap_len = bitmap.len().div_ceil(8);
+    let presence = (!bitmap.is_empty())
+        .then(|| quote!(let bitmap = reader.read_bytes(#bitmap_len)?;));
+    let statements = fields.iter().map(|(member, _, skip)| {
+        if *skip {
+            quote!(self.#member = ::std::default::Default::default();)
+        } else if let Some(bit) = bitmap.iter().position(|m| m == member) {
+            let (index, mask) = (bit / 8, 1u8 << (bit % 8));
+            quote! {
+                self.#member = if bitmap[#index] & #mask != 0 {
+                    Some(::std::default::Default::default())
+                } else {
+                    None
+                };
+                if let Some(value) = &mut self.#member {
+                    value.decode(&mut *reader)?;
+                }
+            }
+        } else {
+            quote!(self.#member.decode(&mut *reader)?;)
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::binary_stream::Decodable for #name #ty_generics
+        #where_clause
+        {
+            fn decode<R: ::std::io::Read + ::std::io::Seek>(
+                &mut self,
+                reader: &mut ::binary_stream::BinaryReader<R>,
+            ) -> ::std::io::Result<()> {
+                #presence
+                #(#statements)*
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Collect the fields of a struct in declaration order with
+/// whether each field is skipped.
+fn struct_fields(input: &DeriveInput) -> Result<Vec<(Member, &Field, bool)>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "only structs can be derived",
+        ));
+    };
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unnamed(fields) => &fields.unnamed,
+        Fields::Unit => return Ok(Vec::new()),
+    };
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(index)),
+            };
+            Ok((member, field, is_skipped(field)?))
+        })
+        .collect()
+}
+
+/// Members of the `Option` fields written in the presence bitmap
+/// when the struct has the `#[binary(option_bitmap)]` attribute.
+fn option_bitmap(
+    input: &DeriveInput,
+    fields: &[(Member, &Field, bool)],
+) -> Result<Vec<Member>> {
+    let mut enabled = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("binary")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("option_bitmap") {
+                enabled = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown binary attribute"))
+            }
+        })?;
+    }
+    if !enabled {
+        return Ok(Vec::new());
+    }
+    Ok(fields
+        .iter()
+        .filter(|(_, field, skip)| !skip && option_inner(&field.ty).is_some())
+        .map(|(member, _, _)| member.clone())
+        .collect())
+}
+
+/// Get the type `T` of a type written as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+/// Determine if a field has the `#[binary(skip)]` attribute.
+fn is_skipped(field: &Field) -> Result<bool> {
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("binary")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown binary attribute"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
+
+/// Add a trait bound to every type parameter and bound the type
+/// of each encoded field so the requirements of container
+/// implementations such as `Vec<T>` carry over.
+///
+/// Skipped fields are bound by `skipped_bound` when given.
+fn add_bounds(
+    generics: &Generics,
+    fields: &[(Member, &Field, bool)],
+    bound: Path,
+    skipped_bound: Option<Path>,
+) -> Generics {
+    let mut generics = generics.clone();
+    if generics.type_params().next().is_none() {
+        return generics;
+    }
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    let where_clause = generics.make_where_clause();
+    for (_, field, skip) in fields {
+        let ty = &field.ty;
+        if !skip {
+            where_clause.predicates.push(parse_quote!(#ty: #bound));
+        } else if let Some(skipped_bound) = &skipped_bound {
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: #skipped_bound));
+        }
+    }
+    generics
+}
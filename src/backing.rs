@@ -0,0 +1,243 @@
+//! Access to the bytes backing a stream held in memory.
+use std::{
+    fs::File,
+    io::{Cursor, Error, ErrorKind, Read, Result, Seek},
+};
+
+#[cfg(feature = "bytemuck")]
+use crate::Endian;
+use crate::{guard_size, BinaryReader, SeekableBuffer};
+
+/// Streams that may expose the bytes backing them.
+pub trait BackingSlice {
+    /// Get the full backing slice when the stream is held in
+    /// memory.
+    fn backing_slice(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<T: AsRef<[u8]>> BackingSlice for Cursor<T> {
+    fn backing_slice(&self) -> Option<&[u8]> {
+        Some(self.get_ref().as_ref())
+    }
+}
+
+impl BackingSlice for SeekableBuffer {
+    fn backing_slice(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
+}
+
+impl BackingSlice for File {}
+
+impl<S: BackingSlice + ?Sized> BackingSlice for &mut S {
+    fn backing_slice(&self) -> Option<&[u8]> {
+        (**self).backing_slice()
+    }
+}
+
+impl<R: Read + Seek + BackingSlice> BinaryReader<R> {
+    /// Get the full slice backing the underlying stream or `None`
+    /// when the stream is not held in memory.
+    ///
+    /// The slice is independent of the current stream position.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        self.stream.backing_slice()
+    }
+}
+
+impl<'a> BinaryReader<Cursor<&'a [u8]>> {
+    /// Split a reader over a slice into readers over the bytes
+    /// before and after `mid` without copying.
+    ///
+    /// Both readers start at position zero and use the options
+    /// for this reader.
+    pub fn split_at(self, mid: u64) -> Result<(Self, Self)> {
+        let buffer = *self.stream.get_ref();
+        if mid > buffer.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("split {} is out of range", mid),
+            ));
+        }
+        let (first, second) = buffer.split_at(mid as usize);
+        Ok((
+            BinaryReader::new(Cursor::new(first), self.options.clone()),
+            BinaryReader::new(Cursor::new(second), self.options),
+        ))
+    }
+
+    /// Read a length-prefixed string borrowed from the backing
+    /// buffer without allocating.
+    pub fn read_str_borrowed(&mut self) -> Result<&'a str> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        self.options.check_string_len(length)?;
+        guard_size!(length, self.options.max_buffer_size);
+        let buffer: &'a [u8] = self.stream.get_ref();
+        let start = self.stream.position();
+        let bytes = start
+            .checked_add(length)
+            .and_then(|end| buffer.get(start as usize..end as usize))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "string exceeds the end of the buffer",
+                )
+            })?;
+        let value = std::str::from_utf8(bytes)
+            .map_err(|_| Error::other("invalid utf-8"))?;
+        self.charge_budget(length)?;
+        self.stream.set_position(start + length);
+        self.skip_padding()?;
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'a> BinaryReader<Cursor<&'a [u8]>> {
+    /// Read `count` values of `u32` as a slice borrowed from the
+    /// backing buffer without copying.
+    ///
+    /// Errors if the endian for the reader is not the native byte
+    /// order or the values are not aligned in memory so callers can
+    /// fall back to reading the values individually.
+    pub fn read_u32_slice_ref(&mut self, count: usize) -> Result<&'a [u32]> {
+        if self.options.endian.resolve() != Endian::native() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "endian is not the native byte order",
+            ));
+        }
+        let length = count.checked_mul(4).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, "slice length overflows")
+        })?;
+        let buffer: &'a [u8] = self.stream.get_ref();
+        let start = self.stream.position() as usize;
+        let bytes = start
+            .checked_add(length)
+            .and_then(|end| buffer.get(start..end))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "slice exceeds the end of the buffer",
+                )
+            })?;
+        let values = bytemuck::try_cast_slice(bytes).map_err(|e| {
+            Error::new(ErrorKind::InvalidInput, format!("{}", e))
+        })?;
+        self.charge_budget(length as u64)?;
+        self.stream.set_position((start + length) as u64);
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use std::{io::Cursor, thread};
+
+    #[test]
+    fn reader_as_slice() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u16(16)?;
+        writer.write_u8(8)?;
+
+        let reader = BinaryReader::new(&mut stream, Default::default());
+        assert_eq!(Some(&[16u8, 0, 8][..]), reader.as_slice());
+
+        let file = tempfile::tempfile()?;
+        let reader = BinaryReader::new(file, Default::default());
+        assert!(reader.as_slice().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_split_at() -> Result<()> {
+        let buffer: Vec<u8> = (0..16).collect();
+        let reader =
+            BinaryReader::new(Cursor::new(&buffer[..]), Default::default());
+        assert!(BinaryReader::new(
+            Cursor::new(&buffer[..]),
+            Default::default()
+        )
+        .split_at(17)
+        .is_err());
+
+        let (mut first, mut second) = reader.split_at(8)?;
+        let (first, second) = thread::scope(|scope| {
+            let first = scope.spawn(move || first.read_u64());
+            let second = scope.spawn(move || second.read_u64());
+            (first.join().unwrap(), second.join().unwrap())
+        });
+        assert_eq!(0x0706_0504_0302_0100, first?);
+        assert_eq!(0x0f0e_0d0c_0b0a_0908, second?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_str_borrowed() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string("alpha")?;
+        writer.write_string("beta")?;
+        let buffer = stream.into_inner();
+
+        let mut reader = BinaryReader::new(
+            Cursor::new(buffer.as_slice()),
+            Default::default(),
+        );
+        let first = reader.read_str_borrowed()?;
+        let second = reader.read_str_borrowed()?;
+        assert_eq!(("alpha", "beta"), (first, second));
+        assert_eq!(
+            buffer.as_ptr_range().end,
+            second.as_bytes().as_ptr_range().end
+        );
+        assert!(reader.read_str_borrowed().is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn read_u32_slice_ref() -> Result<()> {
+        use crate::{Endian, Options};
+
+        let values: Vec<u32> = vec![1, 2, 3, 4];
+        let buffer: &[u8] = bytemuck::cast_slice(&values);
+        let native = Endian::native();
+
+        let mut reader =
+            BinaryReader::new(Cursor::new(buffer), native.into());
+        assert_eq!(1, reader.read_u32()?);
+        let slice = reader.read_u32_slice_ref(2)?;
+        assert_eq!(&[2, 3], slice);
+        assert_eq!(4, reader.read_u32()?);
+        assert!(reader.read_u32_slice_ref(1).is_err());
+
+        let mut reader =
+            BinaryReader::new(Cursor::new(&buffer[1..]), native.into());
+        assert!(reader.read_u32_slice_ref(1).is_err());
+
+        let options = Options {
+            endian: match native {
+                Endian::Little => Endian::Big,
+                _ => Endian::Little,
+            },
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(Cursor::new(buffer), options);
+        assert!(reader.read_u32_slice_ref(1).is_err());
+
+        Ok(())
+    }
+}
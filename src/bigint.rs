@@ -0,0 +1,85 @@
+//! Fixed width big integers using the `num-bigint` crate.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use num_bigint::BigUint;
+
+use crate::{guard_size, BinaryReader, BinaryWriter, Endian};
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Read an unsigned big integer stored as a magnitude of
+    /// exactly `byte_len` bytes.
+    pub fn read_biguint(
+        &mut self,
+        byte_len: usize,
+        endian: Endian,
+    ) -> Result<BigUint> {
+        guard_size!(byte_len, self.options.max_buffer_size);
+        let mut bytes = vec![0; byte_len];
+        self.fill_buffer(&mut bytes)?;
+        Ok(match endian.resolve() {
+            Endian::Big => BigUint::from_bytes_be(&bytes),
+            _ => BigUint::from_bytes_le(&bytes),
+        })
+    }
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Write an unsigned big integer as a magnitude of exactly
+    /// `byte_len` bytes padded with zeros.
+    ///
+    /// Errors if the value does not fit in `byte_len` bytes.
+    pub fn write_biguint(
+        &mut self,
+        value: &BigUint,
+        byte_len: usize,
+        endian: Endian,
+    ) -> Result<usize> {
+        let len = value.bits().div_ceil(8) as usize;
+        if len > byte_len {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "big integer of {} bytes exceeds width {}",
+                    len, byte_len
+                ),
+            ));
+        }
+        let mut bytes = vec![0; byte_len];
+        match endian.resolve() {
+            Endian::Big => bytes[byte_len - len..]
+                .copy_from_slice(&value.to_bytes_be()[..len]),
+            _ => bytes[..len].copy_from_slice(&value.to_bytes_le()[..len]),
+        }
+        self.stream.write_all(&bytes)?;
+        Ok(byte_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BinaryReader, BinaryWriter, Endian};
+    use anyhow::Result;
+    use num_bigint::BigUint;
+    use std::io::{Cursor, SeekFrom};
+
+    #[test]
+    fn read_write_biguint() -> Result<()> {
+        let value = (BigUint::from(1u8) << 380u32) + 12345u32;
+        for endian in [Endian::Little, Endian::Big] {
+            let mut buffer = Vec::new();
+            let mut stream = Cursor::new(&mut buffer);
+            let mut writer =
+                BinaryWriter::new(&mut stream, Default::default());
+            assert_eq!(48, writer.write_biguint(&value, 48, endian)?);
+            assert!(writer.write_biguint(&value, 47, endian).is_err());
+
+            let mut reader =
+                BinaryReader::new(&mut stream, Default::default());
+            reader.seek(SeekFrom::Start(0))?;
+            assert_eq!(value, reader.read_biguint(48, endian)?);
+            assert_eq!(48, buffer.len());
+        }
+
+        Ok(())
+    }
+}
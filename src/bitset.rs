@@ -0,0 +1,61 @@
+//! Encode and decode implementations for `BitVec`.
+use std::io::{Read, Result, Seek, Write};
+
+use bitvec::{order::BitOrder, store::BitStore, vec::BitVec};
+
+use crate::{BinaryReader, BinaryWriter, Decodable, Encodable};
+
+/// Bits are encoded as a `u64` bit count followed by the bits
+/// packed into bytes with the first bit in the least significant
+/// bit of the first byte.
+impl<T: BitStore, O: BitOrder> Encodable for BitVec<T, O> {
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        let mut bytes = vec![0u8; self.len().div_ceil(8)];
+        for index in self.iter_ones() {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+        writer.write_u64(self.len() as u64)?;
+        writer.write_bytes(bytes)?;
+        Ok(())
+    }
+}
+
+impl<T: BitStore, O: BitOrder> Decodable for BitVec<T, O> {
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = reader.read_u64()? as usize;
+        let bytes = reader.read_bytes(len.div_ceil(8))?;
+        self.clear();
+        self.reserve(len);
+        for index in 0..len {
+            self.push(bytes[index / 8] & (1 << (index % 8)) != 0);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode, encode};
+    use anyhow::Result;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    #[test]
+    fn encode_decode_bitvec() -> Result<()> {
+        let bits: BitVec = (0..100).map(|index| index % 3 == 0).collect();
+        let buffer = encode(&bits, Default::default())?;
+        assert_eq!(8 + 13, buffer.len());
+        assert_eq!(bits, decode::<BitVec>(&buffer, Default::default())?);
+
+        let decoded: BitVec<u8, Msb0> = decode(&buffer, Default::default())?;
+        assert_eq!(100, decoded.len());
+        assert!(decoded.iter().by_vals().eq(bits.iter().by_vals()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Write and verify a trailing checksum in a single forward pass.
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+
+use crate::{crc32::Crc32, BinaryReader, BinaryWriter};
+
+/// Computes a running CRC-32 checksum of the bytes read from an
+/// inner stream that does not need to support seeking.
+///
+/// Seeking is only supported to query the current position so
+/// the reader can be used with a `BinaryReader` for forward only
+/// decoding such as from a pipe or socket.
+pub struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+    position: u64,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    /// Create a checksum reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            crc: Crc32::default(),
+            position: 0,
+        }
+    }
+
+    /// Get the checksum of the bytes read so far.
+    pub fn checksum(&self) -> u32 {
+        self.crc.digest()
+    }
+
+    /// Consume this reader and return the inner stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R> Seek for ChecksumReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                "checksum reader can only read forwards",
+            )),
+        }
+    }
+}
+
+impl<R: Read> BinaryReader<ChecksumReader<R>> {
+    /// Read a trailing `u32` checksum and verify it matches the
+    /// CRC-32 checksum of the bytes read before it.
+    pub fn verify_trailing_crc(&mut self) -> Result<()> {
+        let actual = self.stream.checksum();
+        let expected = self.read_u32()?;
+        if actual != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "checksum {:#010x} does not match {:#010x}",
+                    actual, expected
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Computes a running CRC-32 checksum of the bytes written to an
+/// inner stream that does not need to support seeking.
+///
+/// The checksum covers every byte written including length
+/// prefixes and padding. Seeking is only supported to query the
+/// current position.
+pub struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+    position: u64,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    /// Create a checksum writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc: Crc32::default(),
+            position: 0,
+        }
+    }
+
+    /// Get the checksum of the bytes written so far.
+    pub fn checksum(&self) -> u32 {
+        self.crc.digest()
+    }
+
+    /// Consume this writer and return the inner stream.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W> Seek for ChecksumWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                "checksum writer can only write forwards",
+            )),
+        }
+    }
+}
+
+impl<W: Write> BinaryWriter<ChecksumWriter<W>> {
+    /// Write the CRC-32 checksum of the bytes written so far as
+    /// a trailing `u32` to be verified by `verify_trailing_crc`.
+    pub fn write_trailing_crc(&mut self) -> Result<usize> {
+        let crc = self.stream.checksum();
+        self.write_u32(crc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChecksumReader, ChecksumWriter};
+    use crate::{crc32::checksum, BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use std::io::Cursor;
+
+    #[test]
+    fn verify_trailing_crc() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(32)?;
+        writer.write_string("foo")?;
+        let crc = checksum(stream.get_ref());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(crc)?;
+
+        // A byte slice implements `Read` but not `Seek`
+        let stream = ChecksumReader::new(buffer.as_slice());
+        let mut reader = BinaryReader::new(stream, Default::default());
+        assert_eq!(32, reader.read_u32()?);
+        assert_eq!("foo", reader.read_string()?);
+        reader.verify_trailing_crc()?;
+
+        buffer[0] ^= 0xff;
+        let stream = ChecksumReader::new(buffer.as_slice());
+        let mut reader = BinaryReader::new(stream, Default::default());
+        assert_eq!(32 ^ 0xff, reader.read_u32()?);
+        assert_eq!("foo", reader.read_string()?);
+        assert!(reader.verify_trailing_crc().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_trailing_crc() -> Result<()> {
+        let mut writer = BinaryWriter::new(
+            ChecksumWriter::new(Vec::new()),
+            Default::default(),
+        );
+        writer.write_u32(32)?;
+        writer.write_string("foo")?;
+        let crc = writer.get_ref().checksum();
+        assert_eq!(4, writer.write_trailing_crc()?);
+        let buffer = writer.into_inner().into_inner();
+        assert_eq!(checksum(&buffer[..buffer.len() - 4]), crc);
+
+        let stream = ChecksumReader::new(buffer.as_slice());
+        let mut reader = BinaryReader::new(stream, Default::default());
+        assert_eq!(32, reader.read_u32()?);
+        assert_eq!("foo", reader.read_string()?);
+        reader.verify_trailing_crc()?;
+
+        Ok(())
+    }
+}
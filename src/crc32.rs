@@ -0,0 +1,73 @@
+//! CRC-32 (IEEE) checksum.
+
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Running CRC-32 checksum.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self { state: u32::MAX }
+    }
+}
+
+impl Crc32 {
+    /// Update the checksum with bytes.
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            let index = ((self.state ^ *byte as u32) & 0xff) as usize;
+            self.state = (self.state >> 8) ^ TABLE[index];
+        }
+    }
+
+    /// Get the checksum of the bytes seen so far.
+    pub fn digest(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// Compute the CRC-32 checksum of a buffer.
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc32::default();
+    crc.update(data);
+    crc.digest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, Crc32};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(0xcbf4_3926, checksum(b"123456789"));
+        assert_eq!(0, checksum(b""));
+
+        let mut crc = Crc32::default();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(0xcbf4_3926, crc.digest());
+    }
+}
@@ -0,0 +1,70 @@
+//! Encode and decode implementations for `Decimal`.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use rust_decimal::Decimal;
+
+use crate::{BinaryReader, BinaryWriter, Decodable, Encodable};
+
+/// Decimals are encoded as the `i128` mantissa followed by
+/// the `u8` scale.
+impl Encodable for Decimal {
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        writer.write_i128(self.mantissa())?;
+        writer.write_u8(self.scale() as u8)?;
+        Ok(())
+    }
+}
+
+impl Decodable for Decimal {
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let mantissa = reader.read_i128()?;
+        let scale = reader.read_u8()?;
+        *self = Decimal::try_from_i128_with_scale(mantissa, scale as u32)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode, encode, Endian};
+    use anyhow::Result;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn encode_decode_decimal() -> Result<()> {
+        let values = [
+            Decimal::new(12345, 2),
+            Decimal::from_i128_with_scale(
+                -79_228_162_514_264_337_593_543_950_335,
+                28,
+            ),
+            Decimal::ZERO,
+        ];
+        for endian in [Endian::Little, Endian::Big] {
+            for value in values {
+                let buffer = encode(&value, endian.into())?;
+                assert_eq!(17, buffer.len());
+                assert_eq!(value, decode::<Decimal>(&buffer, endian.into())?);
+            }
+        }
+
+        // Scale above the maximum of 28
+        let mut buffer = encode(&Decimal::new(1, 0), Default::default())?;
+        buffer[16] = 29;
+        assert!(decode::<Decimal>(&buffer, Default::default()).is_err());
+
+        // Mantissa wider than 96 bits
+        let mut buffer = encode(&i128::MAX, Default::default())?;
+        buffer.push(0);
+        assert!(decode::<Decimal>(&buffer, Default::default()).is_err());
+
+        Ok(())
+    }
+}
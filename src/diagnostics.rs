@@ -0,0 +1,154 @@
+//! Decode errors annotated with the bytes around the failure.
+use std::{
+    fmt,
+    io::{Error, Read, Result, Seek, SeekFrom},
+};
+
+use crate::{BinaryReader, Decodable};
+
+/// Number of bytes of context either side of the failing offset.
+const CONTEXT_LEN: u64 = 8;
+
+/// Decode error with the offset and surrounding bytes.
+///
+/// Wrapped in the `io::Error` returned when decoding so the
+/// error kind is preserved; use `get_ref` and `downcast_ref` on
+/// the returned error to access it.
+#[derive(Debug)]
+pub struct DecodeError {
+    offset: u64,
+    start: u64,
+    context: Vec<u8>,
+    source: Error,
+}
+
+impl DecodeError {
+    /// Stream position when the error occurred.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Bytes around the failing offset.
+    pub fn context(&self) -> &[u8] {
+        &self.context
+    }
+
+    /// Consume this error and return the original error.
+    pub fn into_inner(self) -> Error {
+        self.source
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {:#x}, bytes from {:#x}:",
+            self.source, self.offset, self.start
+        )?;
+        for (index, byte) in self.context.iter().enumerate() {
+            if self.start + index as u64 == self.offset {
+                write!(f, " [{:02x}]", byte)?;
+            } else {
+                write!(f, " {:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Decode a value and annotate any error with the offset
+    /// and the bytes around the failing offset.
+    ///
+    /// The `decode` and `decode_stream` functions use this
+    /// when the `diagnostics` feature is enabled.
+    pub fn decode_diagnostic<T: Decodable + Default>(&mut self) -> Result<T> {
+        let mut value = T::default();
+        match value.decode(&mut *self) {
+            Ok(_) => Ok(value),
+            Err(e) => Err(self.annotate(e)),
+        }
+    }
+
+    /// Wrap an error with the bytes around the current position.
+    fn annotate(&mut self, source: Error) -> Error {
+        let offset = match self.stream.stream_position() {
+            Ok(offset) => offset,
+            Err(_) => return source,
+        };
+        let start = offset.saturating_sub(CONTEXT_LEN);
+        let mut context = Vec::new();
+        let read = self
+            .stream
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| {
+                (&mut self.stream)
+                    .take(CONTEXT_LEN * 2)
+                    .read_to_end(&mut context)
+            })
+            .and_then(|_| self.stream.seek(SeekFrom::Start(offset)));
+        if read.is_err() {
+            return source;
+        }
+        Error::new(
+            source.kind(),
+            DecodeError {
+                offset,
+                start,
+                context,
+                source,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DecodeError;
+    use crate::{decode, BinaryReader, Decodable};
+    use anyhow::Result;
+    use std::io::{self, Read, Seek};
+
+    #[derive(Debug, Default)]
+    struct Record {
+        id: u64,
+        letter: char,
+    }
+
+    impl Decodable for Record {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            self.id.decode(&mut *reader)?;
+            self.letter.decode(&mut *reader)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn decode_error_hex_context() -> Result<()> {
+        let mut buffer = 1u64.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&0xd800u32.to_le_bytes());
+        buffer.extend_from_slice(&[0xaa, 0xbb]);
+
+        let err = decode::<Record>(&buffer, Default::default()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("invalid character at offset 0xc"));
+        assert!(message
+            .ends_with("bytes from 0x4: 00 00 00 00 00 d8 00 00 [aa] bb"));
+
+        let inner =
+            err.get_ref().and_then(|e| e.downcast_ref::<DecodeError>());
+        assert_eq!(Some(12), inner.map(|e| e.offset()));
+
+        Ok(())
+    }
+}
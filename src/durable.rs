@@ -0,0 +1,260 @@
+//! Durable writes for streams backed by storage.
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Cursor, Error, ErrorKind, Result, Seek, Write},
+    path::Path,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{BinaryWriter, Encodable, Options};
+
+/// Streams that can ensure written data reaches storage.
+pub trait SyncStream {
+    /// Ensure flushed data has reached storage.
+    ///
+    /// The default implementation does nothing which is correct
+    /// for streams that are held in memory.
+    fn sync_all(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl SyncStream for File {
+    fn sync_all(&mut self) -> Result<()> {
+        File::sync_all(self)
+    }
+}
+
+impl<T> SyncStream for Cursor<T> {}
+
+impl<W: Write + SyncStream> SyncStream for BufWriter<W> {
+    fn sync_all(&mut self) -> Result<()> {
+        self.get_mut().sync_all()
+    }
+}
+
+impl<S: SyncStream + ?Sized> SyncStream for &mut S {
+    fn sync_all(&mut self) -> Result<()> {
+        (**self).sync_all()
+    }
+}
+
+impl<W: Write + Seek + SyncStream> BinaryWriter<W> {
+    /// Flush the write buffer and ensure the data has reached
+    /// storage when the stream is a file.
+    pub fn sync(&mut self) -> Result<()> {
+        self.stream.flush()?;
+        self.stream.sync_all()
+    }
+}
+
+/// Streams that can be truncated or extended to a length.
+pub trait SetLen {
+    /// Set the length of the stream.
+    ///
+    /// Extending the stream fills the new bytes with zeros and the
+    /// position is not changed.
+    fn set_len(&mut self, len: u64) -> Result<()>;
+}
+
+impl SetLen for File {
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        File::set_len(self, len)
+    }
+}
+
+impl SetLen for Cursor<Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.get_mut().resize(cursor_len(len)?, 0);
+        Ok(())
+    }
+}
+
+impl SetLen for Cursor<&mut Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.get_mut().resize(cursor_len(len)?, 0);
+        Ok(())
+    }
+}
+
+impl<W: Write + SetLen> SetLen for BufWriter<W> {
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.flush()?;
+        self.get_mut().set_len(len)
+    }
+}
+
+impl<S: SetLen + ?Sized> SetLen for &mut S {
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        (**self).set_len(len)
+    }
+}
+
+/// Convert a length for an in-memory buffer.
+fn cursor_len(len: u64) -> Result<usize> {
+    usize::try_from(len).map_err(|_| {
+        Error::new(ErrorKind::InvalidInput, "length overflows usize")
+    })
+}
+
+impl<W: Write + Seek + SetLen> BinaryWriter<W> {
+    /// Seek to the start of the stream, truncate it and replace
+    /// the options so the writer can be reused without leaving
+    /// bytes from a longer previous encoding.
+    pub fn reset_truncate(&mut self, options: Options) -> Result<()> {
+        self.reset(options)?;
+        self.stream.set_len(0)
+    }
+}
+
+/// Counter for unique temporary file names within the process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Encode to a file replacing the file atomically.
+///
+/// The encoding is written to a new temporary file in the same
+/// directory which is synced to storage and then renamed over
+/// the target so the target never contains a partial encoding.
+/// On Unix the directory is synced after the rename so the new
+/// entry is durable.
+pub fn write_atomic(
+    path: impl AsRef<Path>,
+    encodable: &impl Encodable,
+    options: Options,
+) -> Result<()> {
+    let path = path.as_ref();
+    let file_name = path.file_name().ok_or_else(|| {
+        Error::new(ErrorKind::InvalidInput, "path must name a file")
+    })?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = File::create_new(&temp_path).and_then(|file| {
+        let mut writer = BinaryWriter::new(BufWriter::new(file), options);
+        encodable.encode(&mut writer)?;
+        writer.sync()
+    });
+    if let Err(e) = result.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    #[cfg(unix)]
+    {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_atomic;
+    use crate::{encode, BinaryReader, BinaryWriter, Encodable};
+    use anyhow::Result;
+    use std::{
+        fs::{self, File},
+        io::{self, BufWriter, Cursor, Error, Seek, Write},
+    };
+
+    #[test]
+    fn writer_sync() -> Result<()> {
+        let file = tempfile::NamedTempFile::new()?;
+        let stream = BufWriter::new(file.reopen()?);
+        let mut writer = BinaryWriter::new(stream, Default::default());
+        writer.write_u32(32)?;
+        writer.sync()?;
+
+        let mut reader =
+            BinaryReader::new(File::open(file.path())?, Default::default());
+        assert_eq!(32, reader.read_u32()?);
+
+        let mut writer =
+            BinaryWriter::new(Cursor::new(Vec::new()), Default::default());
+        writer.write_u8(8)?;
+        writer.sync()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn writer_reset_truncate() -> Result<()> {
+        let mut writer =
+            BinaryWriter::new(Cursor::new(Vec::new()), Default::default());
+        writer.write_string("longer")?;
+        writer.reset_truncate(Default::default())?;
+        writer.write_string("short")?;
+        let buffer = writer.into_inner().into_inner();
+        assert_eq!(
+            encode(&String::from("short"), Default::default())?,
+            buffer
+        );
+
+        let file = tempfile::NamedTempFile::new()?;
+        let mut writer = BinaryWriter::new(
+            BufWriter::new(file.reopen()?),
+            Default::default(),
+        );
+        writer.write_u64(1)?;
+        writer.reset_truncate(Default::default())?;
+        writer.write_u8(2)?;
+        writer.flush()?;
+        assert_eq!(vec![2], fs::read(file.path())?);
+
+        Ok(())
+    }
+
+    struct Interrupted;
+
+    impl Encodable for Interrupted {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            writer.write_u32(32)?;
+            Err(Error::other("interrupted"))
+        }
+    }
+
+    #[test]
+    fn write_atomic_replace() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("state.bin");
+        let value = vec![String::from("foo"), String::from("bar")];
+        write_atomic(&path, &value, Default::default())?;
+        assert_eq!(encode(&value, Default::default())?, fs::read(&path)?);
+
+        assert!(
+            write_atomic(&path, &Interrupted, Default::default()).is_err()
+        );
+        assert_eq!(encode(&value, Default::default())?, fs::read(&path)?);
+        assert_eq!(1, fs::read_dir(dir.path())?.count());
+
+        // Concurrent writers use distinct temporary files
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8u32)
+                .map(|i| {
+                    let path = &path;
+                    scope.spawn(move || {
+                        write_atomic(path, &i, Default::default())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().unwrap())
+        })?;
+        assert_eq!(4, fs::read(&path)?.len());
+        assert_eq!(1, fs::read_dir(dir.path())?.count());
+
+        Ok(())
+    }
+}
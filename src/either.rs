@@ -0,0 +1,84 @@
+//! Encode and decode implementations for `Either`.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use either::Either;
+
+use crate::{BinaryReader, BinaryWriter, Decodable, Encodable};
+
+impl<L: Encodable, R: Encodable> Encodable for Either<L, R> {
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        match self {
+            Either::Left(value) => {
+                writer.write_u8(0)?;
+                value.encode(&mut *writer)
+            }
+            Either::Right(value) => {
+                writer.write_u8(1)?;
+                value.encode(&mut *writer)
+            }
+        }
+    }
+}
+
+impl<L, R> Decodable for Either<L, R>
+where
+    L: Decodable + Default,
+    R: Decodable + Default,
+{
+    fn decode<S: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<S>,
+    ) -> Result<()> {
+        *self = match reader.read_u8()? {
+            0 => {
+                let mut value: L = Default::default();
+                value.decode(&mut *reader)?;
+                Either::Left(value)
+            }
+            1 => {
+                let mut value: R = Default::default();
+                value.decode(&mut *reader)?;
+                Either::Right(value)
+            }
+            tag => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid either tag {}", tag),
+                ))
+            }
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encode, BinaryReader, Decodable};
+    use anyhow::Result;
+    use either::Either;
+    use std::io::Cursor;
+
+    #[test]
+    fn encode_decode_either() -> Result<()> {
+        let values: [Either<u32, String>; 2] =
+            [Either::Left(42), Either::Right("right".to_owned())];
+        for value in values {
+            let buffer = encode(&value, Default::default())?;
+            let mut reader =
+                BinaryReader::new(Cursor::new(&buffer), Default::default());
+            let mut decoded: Either<u32, String> = Either::Left(0);
+            decoded.decode(&mut reader)?;
+            assert_eq!(value, decoded);
+        }
+
+        let mut reader =
+            BinaryReader::new(Cursor::new([2u8, 0]), Default::default());
+        let mut decoded: Either<u32, String> = Either::Left(0);
+        assert!(decoded.decode(&mut reader).is_err());
+
+        Ok(())
+    }
+}
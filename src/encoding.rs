@@ -0,0 +1,105 @@
+//! Strings in encodings other than UTF-8.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use encoding_rs::Encoding;
+
+use crate::{guard_size, BinaryReader, BinaryWriter};
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Read a length-prefixed string in the given encoding.
+    ///
+    /// The length prefix is the number of bytes in the encoding
+    /// and errors if the bytes are malformed for the encoding.
+    pub fn read_string_encoded(
+        &mut self,
+        encoding: &'static Encoding,
+    ) -> Result<String> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        self.options.check_string_len(length)?;
+        let bytes = self.read_bytes(length as usize)?;
+        encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes)
+            .map(|value| value.into_owned())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid {}", encoding.name()),
+                )
+            })
+    }
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Write a length-prefixed string in the given encoding.
+    ///
+    /// Errors if the string contains characters that cannot be
+    /// mapped to the encoding or if `encoding_rs` cannot encode to
+    /// the encoding, for example UTF-16.
+    pub fn write_string_encoded<S: AsRef<str>>(
+        &mut self,
+        value: S,
+        encoding: &'static Encoding,
+    ) -> Result<usize> {
+        if encoding.output_encoding() != encoding {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("cannot encode strings as {}", encoding.name()),
+            ));
+        }
+        let (bytes, _, unmappable) = encoding.encode(value.as_ref());
+        if unmappable {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("string cannot be encoded as {}", encoding.name()),
+            ));
+        }
+        guard_size!(bytes.len(), self.options.max_buffer_size);
+        let written = if cfg!(feature = "64bit") {
+            self.write_u64(bytes.len() as u64)?
+        } else {
+            self.write_u32(bytes.len() as u32)?
+        };
+        Ok(written + self.write_bytes(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BinaryReader, BinaryWriter, Options};
+    use anyhow::Result;
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1252};
+    use std::io::{Cursor, SeekFrom};
+
+    #[test]
+    fn read_write_string_encoded() -> Result<()> {
+        let value = "こんにちは";
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string_encoded(value, SHIFT_JIS)?;
+        assert!(writer.write_string_encoded(value, WINDOWS_1252).is_err());
+        assert!(writer.write_string_encoded(value, UTF_16LE).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        assert_eq!(prefix + 10, reader.len()?);
+        assert_eq!(value, reader.read_string_encoded(SHIFT_JIS)?);
+
+        // Nothing is written when the bytes exceed max_buffer_size
+        let options = Options {
+            max_buffer_size: Some(4),
+            ..Default::default()
+        };
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, options);
+        assert!(writer.write_string_encoded(value, SHIFT_JIS).is_err());
+        assert!(stream.get_ref().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Streams encrypted using AES-128 in CTR mode.
+use std::io::{Error, Read, Result, Seek, SeekFrom, Write};
+
+use aes::Aes128;
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher, StreamCipherSeek},
+    Ctr128BE,
+};
+
+type Aes128Ctr = Ctr128BE<Aes128>;
+
+/// Create a cipher positioned at an offset in the key stream.
+fn new_cipher(
+    key: &[u8; 16],
+    iv: &[u8; 16],
+    offset: u64,
+) -> Result<Aes128Ctr> {
+    let mut cipher = Aes128Ctr::new(key.into(), iv.into());
+    seek_cipher(&mut cipher, offset)?;
+    Ok(cipher)
+}
+
+/// Move the cipher to an offset in the key stream.
+fn seek_cipher(cipher: &mut Aes128Ctr, offset: u64) -> Result<()> {
+    cipher
+        .try_seek(offset)
+        .map_err(|_| Error::other("offset exceeds the cipher key stream"))
+}
+
+/// Decrypts bytes read from an inner stream.
+///
+/// The key stream offset is the position in the inner stream so
+/// seeking recomputes the counter for the new position.
+pub struct CipherReader<R> {
+    inner: R,
+    cipher: Aes128Ctr,
+}
+
+impl<R: Seek> CipherReader<R> {
+    /// Create a reader that decrypts using a key and IV.
+    pub fn new(mut inner: R, key: &[u8; 16], iv: &[u8; 16]) -> Result<Self> {
+        let offset = inner.stream_position()?;
+        let cipher = new_cipher(key, iv, offset)?;
+        Ok(Self { inner, cipher })
+    }
+
+    /// Consume this reader and return the inner stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CipherReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.cipher.apply_keystream(&mut buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for CipherReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let offset = self.inner.seek(pos)?;
+        seek_cipher(&mut self.cipher, offset)?;
+        Ok(offset)
+    }
+}
+
+/// Encrypts bytes written to an inner stream.
+///
+/// The key stream offset is the position in the inner stream so
+/// seeking recomputes the counter for the new position.
+pub struct CipherWriter<W> {
+    inner: W,
+    cipher: Aes128Ctr,
+}
+
+impl<W: Seek> CipherWriter<W> {
+    /// Create a writer that encrypts using a key and IV.
+    pub fn new(mut inner: W, key: &[u8; 16], iv: &[u8; 16]) -> Result<Self> {
+        let offset = inner.stream_position()?;
+        let cipher = new_cipher(key, iv, offset)?;
+        Ok(Self { inner, cipher })
+    }
+
+    /// Consume this writer and return the inner stream.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CipherWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut encrypted = buf.to_vec();
+        self.cipher.apply_keystream(&mut encrypted);
+        self.inner.write_all(&encrypted)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for CipherWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let offset = self.inner.seek(pos)?;
+        seek_cipher(&mut self.cipher, offset)?;
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CipherReader, CipherWriter};
+    use crate::{BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use std::io::{Cursor, SeekFrom};
+
+    const KEY: [u8; 16] = [7; 16];
+    const IV: [u8; 16] = [9; 16];
+
+    #[test]
+    fn cipher_round_trip() -> Result<()> {
+        let mut buffer = Vec::new();
+        let stream = CipherWriter::new(Cursor::new(&mut buffer), &KEY, &IV)?;
+        let mut writer = BinaryWriter::new(stream, Default::default());
+        for value in 0..16u32 {
+            writer.write_u32(value)?;
+        }
+        writer.write_string("secret")?;
+        drop(writer);
+        assert!(!buffer.windows(6).any(|bytes| bytes == b"secret"));
+
+        let stream = CipherReader::new(Cursor::new(&buffer), &KEY, &IV)?;
+        let mut reader = BinaryReader::new(stream, Default::default());
+        assert_eq!(0, reader.read_u32()?);
+
+        reader.seek(SeekFrom::Start(40))?;
+        assert_eq!(10, reader.read_u32()?);
+        reader.seek(SeekFrom::Start(64))?;
+        assert_eq!("secret", reader.read_string()?);
+        reader.seek(SeekFrom::Start(4))?;
+        assert_eq!(1, reader.read_u32()?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Support for C-style enums using the `num_enum` crate.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use num_enum::TryFromPrimitive;
+
+use crate::{BinaryReader, BinaryWriter, Decodable, Encodable};
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Read the primitive for an enum and convert it to a variant.
+    ///
+    /// Errors if the primitive is not a known discriminant.
+    pub fn read_num_enum<E>(&mut self) -> Result<E>
+    where
+        E: TryFromPrimitive,
+        E::Primitive: Decodable + Default,
+    {
+        let mut value = E::Primitive::default();
+        value.decode(&mut *self)?;
+        E::try_from_primitive(value).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown variant discriminant {:?}", value),
+            )
+        })
+    }
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Write the primitive for an enum variant.
+    pub fn write_num_enum<E>(&mut self, value: E) -> Result<()>
+    where
+        E: TryFromPrimitive + Into<E::Primitive>,
+        E::Primitive: Encodable,
+    {
+        value.into().encode(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use num_enum::{IntoPrimitive, TryFromPrimitive};
+    use std::io::{Cursor, SeekFrom};
+
+    #[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive)]
+    #[repr(u8)]
+    enum Color {
+        Red = 1,
+        Green = 2,
+    }
+
+    #[test]
+    fn read_write_num_enum() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_num_enum(Color::Green)?;
+        writer.write_num_enum(Color::Red)?;
+        writer.write_u8(3)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(Color::Green, reader.read_num_enum::<Color>()?);
+        assert_eq!(Color::Red, reader.read_num_enum::<Color>()?);
+        assert!(reader.read_num_enum::<Color>().is_err());
+        assert_eq!(3, buffer.len());
+
+        Ok(())
+    }
+}
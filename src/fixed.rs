@@ -0,0 +1,43 @@
+//! Stream that writes to a fixed size buffer.
+use std::io::{Cursor, Result, Seek, SeekFrom, Write};
+
+/// Writes to a fixed size buffer and errors with `WriteZero`
+/// rather than writing partially when the buffer is full.
+pub(crate) struct FixedBuffer<'a> {
+    inner: Cursor<&'a mut [u8]>,
+    used: u64,
+}
+
+impl<'a> FixedBuffer<'a> {
+    /// Create a stream that writes to a buffer.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            inner: Cursor::new(buffer),
+            used: 0,
+        }
+    }
+
+    /// Number of bytes from the start of the buffer that have
+    /// been written.
+    pub fn used(&self) -> usize {
+        self.used as usize
+    }
+}
+
+impl Write for FixedBuffer<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.inner.write_all(buf)?;
+        self.used = self.used.max(self.inner.position());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for FixedBuffer<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.inner.seek(pos)
+    }
+}
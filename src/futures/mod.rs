@@ -5,12 +5,27 @@ use futures::io::{
     AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite,
     AsyncWriteExt, BufReader, BufWriter, Cursor,
 };
+use futures::stream::{self, Stream};
 use std::{
+    any::Any,
     borrow::Borrow,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::Display,
+    hash::Hash,
     io::{Error, ErrorKind, Result, SeekFrom},
+    num::Wrapping,
+    ops::{Deref, DerefMut, Range, RangeInclusive},
+    path::{Path, PathBuf},
 };
 
-use crate::{decode_endian, guard_size, Endian, Options};
+use crate::{
+    budget_exceeded, check_alignment, decode_endian, decode_varint,
+    decode_varint_signext, delta_add, delta_sub, encode_path, encode_varint,
+    encode_varint_signext, field_padding, guard_chars, guard_chunk_size,
+    guard_sentinel, guard_size, is_budget_exceeded, no_progress,
+    optional_sentinel, utf8_carry, varint_unterminated, Endian, ErrorPolicy,
+    Options, Presence, Snapshot, SubStream, VARINT_MAX_LEN,
+};
 
 #[cfg(feature = "tokio")]
 use tokio::io::{
@@ -19,15 +34,16 @@ use tokio::io::{
 };
 
 #[cfg(feature = "tokio")]
-use std::io::Cursor;
+use std::{future::Future, io::Cursor, time::Duration};
 
 macro_rules! encode_endian {
     ($endian:expr, $value:expr, $stream:expr) => {
         let data = match $endian {
             Endian::Little => $value.to_le_bytes(),
             Endian::Big => $value.to_be_bytes(),
+            Endian::Native => $value.to_ne_bytes(),
         };
-        return Ok($stream.write(&data).await?);
+        return $stream.write(&data).await;
     };
 }
 
@@ -42,6 +58,21 @@ pub async fn stream_length<S: AsyncSeek + Unpin>(
     Ok(length)
 }
 
+/// Await a read from a stream and error with `TimedOut` if it
+/// does not complete within the duration.
+#[cfg(feature = "tokio")]
+async fn read_timeout<T>(
+    duration: Option<Duration>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(duration) = duration else {
+        return future.await;
+    };
+    tokio::time::timeout(duration, future).await.map_err(|_| {
+        Error::new(ErrorKind::TimedOut, "read from stream timed out")
+    })?
+}
+
 /// Read from a stream.
 pub struct BinaryReader<R>
 where
@@ -49,53 +80,452 @@ where
 {
     stream: R,
     options: Options,
+    budget: Option<u64>,
+    initial_budget: Option<u64>,
+    consumed: u64,
+    context: Option<Box<dyn Any + Send + Sync>>,
+    last_error: Option<Error>,
+    #[cfg(feature = "tokio")]
+    timeout: Option<Duration>,
 }
 
 impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     /// Create a binary reader with the given options.
     pub fn new(stream: R, options: Options) -> Self {
-        Self { stream, options }
+        Self {
+            stream,
+            options,
+            budget: None,
+            initial_budget: None,
+            consumed: 0,
+            context: None,
+            last_error: None,
+            #[cfg(feature = "tokio")]
+            timeout: None,
+        }
+    }
+
+    /// Create a binary reader that errors once more than `budget`
+    /// bytes in total have been read from the stream.
+    ///
+    /// Unlike `max_buffer_size` which limits the size of individual
+    /// strings and byte slices the budget caps the total number of
+    /// bytes read across all calls.
+    pub fn with_byte_budget(
+        stream: R,
+        options: Options,
+        budget: u64,
+    ) -> Self {
+        Self {
+            stream,
+            options,
+            budget: Some(budget),
+            initial_budget: Some(budget),
+            consumed: 0,
+            context: None,
+            last_error: None,
+            #[cfg(feature = "tokio")]
+            timeout: None,
+        }
+    }
+
+    /// Create a binary reader that errors with `TimedOut` if a
+    /// single read from the stream does not complete in time.
+    ///
+    /// The timeout bounds the latency of each read, for example
+    /// of one primitive value or byte buffer, not of a whole decode.
+    #[cfg(feature = "tokio")]
+    pub fn with_timeout(
+        stream: R,
+        options: Options,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            stream,
+            options,
+            budget: None,
+            initial_budget: None,
+            consumed: 0,
+            context: None,
+            last_error: None,
+            timeout: Some(duration),
+        }
+    }
+
+    /// Read exactly enough bytes to fill the buffer and deduct
+    /// the length of the buffer from the byte budget.
+    async fn fill_buffer(&mut self, buffer: &mut [u8]) -> Result<()> {
+        self.charge_budget(buffer.len() as u64)?;
+        #[cfg(feature = "tokio")]
+        read_timeout(self.timeout, self.stream.read_exact(buffer)).await?;
+        #[cfg(not(feature = "tokio"))]
+        self.stream.read_exact(buffer).await?;
+        Ok(())
+    }
+
+    /// Fill the buffer for a primitive value.
+    ///
+    /// When the error policy is `DefaultValue` an unexpected end of
+    /// stream zeroes the buffer and records the error; an exceeded
+    /// byte budget is always returned.
+    async fn fill_primitive(&mut self, buffer: &mut [u8]) -> Result<()> {
+        match self.fill_buffer(buffer).await {
+            Err(e)
+                if e.kind() == ErrorKind::UnexpectedEof
+                    && !is_budget_exceeded(&e)
+                    && self.options.on_read_error
+                        == ErrorPolicy::DefaultValue =>
+            {
+                buffer.fill(0);
+                self.last_error = Some(e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Read up to the length of the buffer and deduct the
+    /// number of bytes read from the byte budget.
+    async fn read_chunk(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        loop {
+            #[cfg(feature = "tokio")]
+            let result = read_timeout(self.timeout, self.stream.read(buffer));
+            #[cfg(not(feature = "tokio"))]
+            let result = self.stream.read(buffer);
+            match result.await {
+                Ok(read) => {
+                    self.charge_budget(read as u64)?;
+                    return Ok(read);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Error if a length exceeds the byte budget so callers can
+    /// check the budget before allocating a buffer.
+    fn check_budget(&self, length: u64) -> Result<()> {
+        match self.budget {
+            Some(remaining) if length > remaining => {
+                Err(budget_exceeded(length, remaining))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Deduct a length from the byte budget.
+    fn charge_budget(&mut self, length: u64) -> Result<()> {
+        self.check_budget(length)?;
+        if let Some(remaining) = self.budget.as_mut() {
+            *remaining -= length;
+        }
+        self.consumed = self.consumed.saturating_add(length);
+        Ok(())
+    }
+
+    /// Seek to the start of the stream, replace the options and
+    /// restore the byte budget so the reader can be reused for
+    /// another pass.
+    pub async fn reset(&mut self, options: Options) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(0)).await?;
+        self.options = options;
+        self.budget = self.initial_budget;
+        self.last_error = None;
+        Ok(())
+    }
+
+    /// Error recorded by the most recent primitive read that
+    /// returned a default value.
+    ///
+    /// Only set when `on_read_error` is `ErrorPolicy::DefaultValue`.
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Get the options for this reader.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Get a mutable reference to the options for this reader.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// Capture the position, options and remaining byte budget
+    /// so a speculative parse can be undone using `restore`.
+    pub async fn snapshot(&mut self) -> Result<Snapshot> {
+        Ok(Snapshot {
+            position: self.stream.stream_position().await?,
+            options: self.options.clone(),
+            budget: self.budget,
+        })
+    }
+
+    /// Seek to the position of a snapshot and restore the options
+    /// and byte budget.
+    pub async fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(snapshot.position)).await?;
+        self.options = snapshot.options;
+        self.budget = snapshot.budget;
+        Ok(())
     }
 
     /// Seek to a position.
     pub async fn seek(&mut self, to: SeekFrom) -> Result<u64> {
-        Ok(self.stream.seek(to).await?)
+        self.stream.seek(to).await
     }
 
     /// Get the current position.
     pub async fn stream_position(&mut self) -> Result<u64> {
-        Ok(self.stream.stream_position().await?)
+        self.stream.stream_position().await
+    }
+
+    /// Seek forward `count` bytes from the current position.
+    pub async fn skip(&mut self, count: u64) -> Result<()> {
+        let offset = i64::try_from(count).map_err(|_| {
+            Error::new(ErrorKind::InvalidInput, "skip count overflows")
+        })?;
+        self.stream.seek(SeekFrom::Current(offset)).await?;
+        Ok(())
+    }
+
+    /// Seek forward over padding until the position is a multiple
+    /// of `alignment` and return the number of padding bytes skipped.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub async fn align_to(&mut self, alignment: u64) -> Result<u64> {
+        check_alignment(alignment)?;
+        let position = self.stream.stream_position().await?;
+        let padding = field_padding(position, alignment);
+        self.skip(padding).await?;
+        Ok(padding)
+    }
+
+    /// Read the next byte without consuming it.
+    ///
+    /// The position and byte budget are restored even if the read
+    /// fails and the end of the stream is always an error.
+    pub async fn peek_u8(&mut self) -> Result<u8> {
+        let position = self.stream.stream_position().await?;
+        let (budget, consumed) = (self.budget, self.consumed);
+        let mut buffer = [0; 1];
+        let result = self.fill_buffer(&mut buffer).await;
+        self.budget = budget;
+        self.consumed = consumed;
+        self.stream.seek(SeekFrom::Start(position)).await?;
+        result.map(|_| buffer[0])
     }
 
     /// Get the length of this stream by seeking to the end
     /// and then restoring the previous cursor position.
+    #[allow(clippy::len_without_is_empty)]
     pub async fn len(&mut self) -> Result<u64> {
         stream_length(&mut self.stream).await
     }
 
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading, writing or seeking the stream directly bypasses
+    /// the options for this reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.stream
+    }
+
+    /// Consume this reader and return the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.stream
+    }
+
+    /// Read the total length written at the end of the stream
+    /// by `finalize_with_trailer` and restore the position.
+    pub async fn read_trailer_length(&mut self) -> Result<u64> {
+        let length = self.len().await?;
+        if length < 8 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for a trailer",
+            ));
+        }
+        let position = self.stream.stream_position().await?;
+        self.stream.seek(SeekFrom::Start(length - 8)).await?;
+        let trailer = self.read_u64().await;
+        self.stream.seek(SeekFrom::Start(position)).await?;
+        trailer
+    }
+
+    /// Compare the bytes at the end of the stream with the footer
+    /// written by `write_footer` and restore the position.
+    ///
+    /// Errors with `InvalidData` if the footer does not match.
+    pub async fn read_footer(&mut self, expected: &[u8]) -> Result<()> {
+        let length = self.len().await?;
+        if length < expected.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for the footer",
+            ));
+        }
+        let position = self.stream.stream_position().await?;
+        self.stream
+            .seek(SeekFrom::Start(length - expected.len() as u64))
+            .await?;
+        let footer = self.read_buffer(expected.len()).await;
+        self.stream.seek(SeekFrom::Start(position)).await?;
+        if footer? != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "footer magic does not match",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Set user state that nested decoders can access using
+    /// `context` and `context_mut`, replacing any existing state.
+    pub fn set_context<T: Any + Send + Sync>(&mut self, context: T) {
+        self.context = Some(Box::new(context));
+    }
+
+    /// Get a reference to the user state if it is of type `T`.
+    pub fn context<T: Any>(&self) -> Option<&T> {
+        self.context.as_ref()?.downcast_ref()
+    }
+
+    /// Get a mutable reference to the user state if it is of type `T`.
+    pub fn context_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.context.as_mut()?.downcast_mut()
+    }
+
+    /// Remove the user state and return it if it is of type `T`.
+    pub fn take_context<T: Any>(&mut self) -> Option<T> {
+        match self.context.take()?.downcast() {
+            Ok(context) => Some(*context),
+            Err(context) => {
+                self.context = Some(context);
+                None
+            }
+        }
+    }
+
     /// Read a length-prefixed `String` from the stream.
     pub async fn read_string(&mut self) -> Result<String> {
         let chars = if cfg!(feature = "64bit") {
             let str_len = self.read_u64().await?;
+            self.options.check_string_len(str_len)?;
             guard_size!(str_len, self.options.max_buffer_size);
+            self.check_budget(str_len)?;
             let mut chars: Vec<u8> = vec![0; str_len as usize];
-            self.stream.read_exact(&mut chars).await?;
+            self.fill_buffer(&mut chars).await?;
             chars
         } else {
             let str_len = self.read_u32().await?;
+            self.options.check_string_len(str_len as u64)?;
             guard_size!(str_len, self.options.max_buffer_size);
+            self.check_budget(str_len as u64)?;
             let mut chars: Vec<u8> = vec![0; str_len as usize];
-            self.stream.read_exact(&mut chars).await?;
+            self.fill_buffer(&mut chars).await?;
             chars
         };
-        Ok(String::from_utf8(chars)
-            .map_err(|_| Error::new(ErrorKind::Other, "invalid utf-8"))?)
+        self.skip_padding().await?;
+        String::from_utf8(chars).map_err(|_| Error::other("invalid utf-8"))
+    }
+
+    /// Read a string from a field of exactly `len` bytes written
+    /// using `write_string_fixed` and trim the trailing NUL bytes.
+    pub async fn read_string_fixed(&mut self, len: usize) -> Result<String> {
+        let chars = self.read_record_field(len).await?;
+        String::from_utf8(chars).map_err(|_| Error::other("invalid utf-8"))
+    }
+
+    /// Read a length-prefixed string and validate it is UTF-8 in
+    /// chunks without allocating or constructing a `String`.
+    ///
+    /// Returns the number of bytes consumed including the length prefix.
+    pub async fn validate_string(&mut self) -> Result<usize> {
+        let (prefix, str_len) = if cfg!(feature = "64bit") {
+            (8, self.read_u64().await?)
+        } else {
+            (4, self.read_u32().await? as u64)
+        };
+        self.options.check_string_len(str_len)?;
+        guard_size!(str_len, self.options.max_buffer_size);
+        self.check_budget(str_len)?;
+        let mut chunk = [0u8; 1024];
+        let (mut remaining, mut pending) = (str_len, 0);
+        while remaining > 0 {
+            let len = remaining.min((chunk.len() - pending) as u64) as usize;
+            self.fill_buffer(&mut chunk[pending..pending + len]).await?;
+            remaining -= len as u64;
+            pending = utf8_carry(&mut chunk, pending + len)?;
+        }
+        if pending > 0 {
+            return Err(Error::other("invalid utf-8"));
+        }
+        self.skip_padding().await?;
+        Ok(prefix + str_len as usize)
+    }
+
+    /// Read a length-prefixed `String` from the stream and
+    /// error if it contains more than `max_chars` characters.
+    pub async fn read_string_max_chars(
+        &mut self,
+        max_chars: usize,
+    ) -> Result<String> {
+        let value = self.read_string().await?;
+        guard_chars!(value, max_chars);
+        Ok(value)
+    }
+
+    /// Read a path written using `write_path`.
+    pub async fn read_path(&mut self) -> Result<PathBuf> {
+        Ok(PathBuf::from(self.read_string().await?))
+    }
+
+    /// Read a UTF-16 string terminated by a zero code unit.
+    ///
+    /// Code units use the endian for the reader and the
+    /// terminator is consumed but not included in the string.
+    pub async fn read_cstring_utf16(&mut self) -> Result<String> {
+        let mut units = Vec::new();
+        loop {
+            let unit = self.read_u16().await?;
+            if unit == 0 {
+                break;
+            }
+            units.push(unit);
+            guard_size!(units.len() * 2, self.options.max_buffer_size);
+        }
+        String::from_utf16(&units)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid utf-16"))
     }
 
     /// Read a character from the stream.
     pub async fn read_char(&mut self) -> Result<char> {
-        std::char::from_u32(self.read_u32().await?)
-            .ok_or_else(|| Error::new(ErrorKind::Other, "invalid character"))
+        let mut buffer: [u8; 4] = [0; 4];
+        self.fill_buffer(&mut buffer).await?;
+        let value = match self.options.char_endian() {
+            Endian::Little => u32::from_le_bytes(buffer),
+            Endian::Big => u32::from_be_bytes(buffer),
+            Endian::Native => u32::from_ne_bytes(buffer),
+        };
+        std::char::from_u32(value)
+            .ok_or_else(|| Error::other("invalid character"))
+    }
+
+    /// Read a presence flag where zero indicates a value is present
+    /// and any other value is the reason the value is absent.
+    pub async fn read_presence(&mut self) -> Result<Presence> {
+        Ok(match self.read_u8().await? {
+            0 => Presence::Present,
+            reason => Presence::Absent(reason),
+        })
     }
 
     /// Read a `bool` from the stream.
@@ -107,14 +537,14 @@ impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     /// Read a `f32` from the stream.
     pub async fn read_f32(&mut self) -> Result<f32> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, f32);
     }
 
     /// Read a `f64` from the stream.
     pub async fn read_f64(&mut self) -> Result<f64> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, f64);
     }
 
@@ -122,7 +552,7 @@ impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     #[cfg(target_pointer_width = "32")]
     pub async fn read_isize(&mut self) -> Result<isize> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, isize);
     }
 
@@ -130,7 +560,7 @@ impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     #[cfg(target_pointer_width = "64")]
     pub async fn read_isize(&mut self) -> Result<isize> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, isize);
     }
 
@@ -138,7 +568,7 @@ impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     #[cfg(target_pointer_width = "32")]
     pub async fn read_usize(&mut self) -> Result<usize> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, usize);
     }
 
@@ -146,87 +576,740 @@ impl<R: AsyncRead + AsyncSeek + Unpin> BinaryReader<R> {
     #[cfg(target_pointer_width = "64")]
     pub async fn read_usize(&mut self) -> Result<usize> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, usize);
     }
 
     /// Read a `u64` from the stream.
     pub async fn read_u64(&mut self) -> Result<u64> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, u64);
     }
 
     /// Read an `i64` from the stream.
     pub async fn read_i64(&mut self) -> Result<i64> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, i64);
     }
 
     /// Read a `u128` from the stream.
     pub async fn read_u128(&mut self) -> Result<u128> {
         let mut buffer: [u8; 16] = [0; 16];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, u128);
     }
 
     /// Read an `i128` from the stream.
     pub async fn read_i128(&mut self) -> Result<i128> {
         let mut buffer: [u8; 16] = [0; 16];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, i128);
     }
 
     /// Read a `u32` from the stream.
     pub async fn read_u32(&mut self) -> Result<u32> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, u32);
     }
 
     /// Read an `i32` from the stream.
     pub async fn read_i32(&mut self) -> Result<i32> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, i32);
     }
 
     /// Read a `u16` from the stream.
     pub async fn read_u16(&mut self) -> Result<u16> {
         let mut buffer: [u8; 2] = [0; 2];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, u16);
     }
 
     /// Read an `i16` from the stream.
     pub async fn read_i16(&mut self) -> Result<i16> {
         let mut buffer: [u8; 2] = [0; 2];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, i16);
     }
 
     /// Read a `u8` from the stream.
     pub async fn read_u8(&mut self) -> Result<u8> {
         let mut buffer: [u8; 1] = [0; 1];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, u8);
     }
 
     /// Read an `i8` from the stream.
     pub async fn read_i8(&mut self) -> Result<i8> {
         let mut buffer: [u8; 1] = [0; 1];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_primitive(&mut buffer).await?;
         decode_endian!(self.options.endian, buffer, i8);
     }
 
+    /// Read a `u64` encoded using unsigned LEB128.
+    pub async fn read_varint_u64(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for index in 0..VARINT_MAX_LEN {
+            let byte = self.read_u8().await?;
+            if decode_varint(&mut value, index, byte)? {
+                return Ok(value);
+            }
+        }
+        Err(varint_unterminated())
+    }
+
+    /// Read an `i64` encoded using signed LEB128 where the sign is
+    /// extended from the high bit of the final group.
+    pub async fn read_varint_i64_signext(&mut self) -> Result<i64> {
+        let mut value = 0;
+        for index in 0..VARINT_MAX_LEN {
+            let byte = self.read_u8().await?;
+            if decode_varint_signext(&mut value, index, byte)? {
+                return Ok(value);
+            }
+        }
+        Err(varint_unterminated())
+    }
+
+    /// Read an `i64` written using `write_varint_i64`.
+    pub async fn read_varint_i64(&mut self) -> Result<i64> {
+        let value = self.read_varint_u64().await?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Read a sequence of `u64` written using `write_delta_varint`.
+    pub async fn read_delta_varint(&mut self) -> Result<Vec<u64>> {
+        let count = self.read_varint_u64().await?;
+        guard_size!(count, self.options.max_buffer_size);
+        let mut values = Vec::new();
+        let mut previous = 0;
+        for _ in 0..count {
+            previous = delta_add(previous, self.read_varint_u64().await?)?;
+            values.push(previous);
+        }
+        Ok(values)
+    }
+
+    /// Read a list of strings written using
+    /// `write_string_list_prefix_compressed`.
+    pub async fn read_string_list_prefix_compressed(
+        &mut self,
+    ) -> Result<Vec<String>> {
+        let count = self.read_varint_u64().await?;
+        guard_size!(count, self.options.max_buffer_size);
+        let mut values: Vec<String> = Vec::new();
+        for _ in 0..count {
+            let shared = self.read_varint_u64().await? as usize;
+            let previous =
+                values.last().map(|v| v.as_bytes()).unwrap_or_default();
+            if shared > previous.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "shared prefix {} exceeds previous length {}",
+                        shared,
+                        previous.len()
+                    ),
+                ));
+            }
+            let mut bytes = previous[..shared].to_vec();
+            bytes.extend_from_slice(self.read_string().await?.as_bytes());
+            values.push(String::from_utf8(bytes).map_err(|_| {
+                Error::new(ErrorKind::InvalidData, "invalid utf-8")
+            })?);
+        }
+        Ok(values)
+    }
+
     /// Read bytes from the stream into a buffer.
+    ///
+    /// Skips any padding written to reach the field alignment.
     pub async fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let buffer = self.read_buffer(length).await?;
+        self.skip_padding().await?;
+        Ok(buffer)
+    }
+
+    /// Read bytes into a buffer ignoring the field alignment.
+    async fn read_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
         guard_size!(length, self.options.max_buffer_size);
+        self.check_budget(length as u64)?;
         let mut buffer: Vec<u8> = vec![0; length];
-        self.stream.read_exact(&mut buffer).await?;
+        self.fill_buffer(&mut buffer).await?;
         Ok(buffer)
     }
+
+    /// Read a field of exactly `width` bytes written using
+    /// `write_record_field` and trim the trailing NUL bytes.
+    pub async fn read_record_field(
+        &mut self,
+        width: usize,
+    ) -> Result<Vec<u8>> {
+        let mut field = self.read_buffer(width).await?;
+        let len = field
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        field.truncate(len);
+        Ok(field)
+    }
+
+    /// Skip padding up to the field alignment.
+    async fn skip_padding(&mut self) -> Result<()> {
+        let Some(alignment) = self.options.field_alignment else {
+            return Ok(());
+        };
+        let position = self.stream_position().await?;
+        let padding = field_padding(position, alignment);
+        if padding > 0 {
+            let mut buffer = vec![0; padding as usize];
+            self.fill_buffer(&mut buffer).await?;
+        }
+        Ok(())
+    }
+
+    /// Read run-length encoded bytes written using `write_rle`
+    /// and expand them to exactly `decoded_len` bytes.
+    pub async fn read_rle(&mut self, decoded_len: usize) -> Result<Vec<u8>> {
+        guard_size!(decoded_len, self.options.max_buffer_size);
+        let mut buffer = Vec::with_capacity(decoded_len);
+        while buffer.len() < decoded_len {
+            let count = self.read_u8().await? as usize;
+            let value = self.read_u8().await?;
+            if count == 0 || buffer.len() + count > decoded_len {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "run-length encoding does not decode to {} bytes",
+                        decoded_len
+                    ),
+                ));
+            }
+            buffer.resize(buffer.len() + count, value);
+        }
+        Ok(buffer)
+    }
+
+    /// Copy the remaining bytes in the stream to a writer and
+    /// return the number of bytes copied.
+    ///
+    /// Bytes are read in chunks of `io_chunk_size`.
+    pub async fn copy_to<W: AsyncWrite + Unpin>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<u64> {
+        guard_chunk_size!(self.options.io_chunk_size);
+        let mut buffer = vec![0; self.options.io_chunk_size];
+        let mut copied = 0;
+        loop {
+            let read = self.read_chunk(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read]).await?;
+            copied += read as u64;
+        }
+        Ok(copied)
+    }
+
+    /// Read the remaining bytes in the stream into a buffer.
+    ///
+    /// Bytes are read in chunks of `io_chunk_size`.
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        guard_chunk_size!(self.options.io_chunk_size);
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0; self.options.io_chunk_size];
+        loop {
+            let read = self.read_chunk(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            guard_size!(buffer.len() + read, self.options.max_buffer_size);
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        Ok(buffer)
+    }
+
+    /// Get a reader bounded to the next `len` bytes, for example
+    /// a length delimited frame.
+    ///
+    /// Async drop cannot seek so call `finish` on the returned
+    /// reader to move this reader to the end of the frame.
+    pub async fn scoped(
+        &mut self,
+        len: u64,
+    ) -> Result<ChildReader<'_, SubStream<&mut R>>> {
+        let start = self.stream.stream_position().await?;
+        let stream = SubStream::from_parts(&mut self.stream, start, len);
+        let budget = self.budget.map(|remaining| remaining.min(len));
+        Ok(ChildReader::new(
+            stream,
+            self.options.clone(),
+            self.budget.as_mut(),
+            &mut self.consumed,
+            budget,
+        ))
+    }
+
+    /// Get a reader that errors once more than `limit` bytes
+    /// have been read.
+    ///
+    /// The limit is a byte budget so this reader is left wherever
+    /// reading stopped.
+    pub fn take(&mut self, limit: u64) -> ChildReader<'_, &mut R> {
+        let budget =
+            self.budget.map_or(limit, |remaining| remaining.min(limit));
+        ChildReader::new(
+            &mut self.stream,
+            self.options.clone(),
+            self.budget.as_mut(),
+            &mut self.consumed,
+            Some(budget),
+        )
+    }
+
+    /// Read a `Vec` encoded as a length followed by the elements
+    /// and keep the elements decoded before an error.
+    ///
+    /// Returns the decoded elements and the error that stopped
+    /// decoding, if any. After an error the stream is positioned at
+    /// the start of the element that failed to decode.
+    pub async fn read_vec_lenient<T>(&mut self) -> (Vec<T>, Option<Error>)
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+    {
+        let mut items = Vec::new();
+        let len = if cfg!(feature = "64bit") {
+            self.read_u64().await
+        } else {
+            self.read_u32().await.map(|len| len as u64)
+        };
+        let len = match len {
+            Ok(len) => len,
+            Err(e) => return (items, Some(e)),
+        };
+        for _ in 0..len {
+            let position = match self.stream_position().await {
+                Ok(position) => position,
+                Err(e) => return (items, Some(e)),
+            };
+            let mut item = T::default();
+            if let Err(e) = item.decode(&mut *self).await {
+                let _ = self.seek(SeekFrom::Start(position)).await;
+                return (items, Some(e));
+            }
+            items.push(item);
+        }
+        (items, None)
+    }
+
+    /// Read exactly `count` elements that were written without
+    /// a length prefix using `write_elements`.
+    pub async fn read_elements<T>(&mut self, count: usize) -> Result<Vec<T>>
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+    {
+        guard_size!(count, self.options.max_buffer_size);
+        let mut items = Vec::new();
+        for _ in 0..count {
+            let mut item = T::default();
+            item.decode(&mut *self).await?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read a `Wide` value and convert it to `Narrow`.
+    ///
+    /// Errors with `InvalidData` if the value does not fit in
+    /// `Narrow` rather than truncating it.
+    pub async fn read_narrowed<Wide, Narrow>(&mut self) -> Result<Narrow>
+    where
+        R: Send,
+        Wide: Decodable + Default + Copy + Display + Send,
+        Narrow: TryFrom<Wide>,
+    {
+        let mut value = Wide::default();
+        value.decode(&mut *self).await?;
+        Narrow::try_from(value).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "value {} does not fit in {}",
+                    value,
+                    std::any::type_name::<Narrow>()
+                ),
+            )
+        })
+    }
+
+    /// Read a `u64` value that must fit in a `u16`.
+    pub async fn read_u16_from_u64(&mut self) -> Result<u16>
+    where
+        R: Send,
+    {
+        self.read_narrowed::<u64, u16>().await
+    }
+
+    /// Read a list written using `write_list_suffix_count` that
+    /// ends at the end of the stream.
+    ///
+    /// The count is read from the end of the stream and the stream
+    /// is positioned after the count once the elements are decoded.
+    pub async fn read_list_suffix_count<T>(&mut self) -> Result<Vec<T>>
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+    {
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        let start = self.stream_position().await?;
+        let end = self.len().await?;
+        if end.saturating_sub(start) < prefix {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for a list count",
+            ));
+        }
+        self.seek(SeekFrom::Start(end - prefix)).await?;
+        let count = if cfg!(feature = "64bit") {
+            self.read_u64().await?
+        } else {
+            self.read_u32().await? as u64
+        };
+        guard_size!(count, self.options.max_buffer_size);
+        self.seek(SeekFrom::Start(start)).await?;
+        let items = self.read_elements(count as usize).await?;
+        if self.stream_position().await? != end - prefix {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "list elements do not end at the count",
+            ));
+        }
+        self.seek(SeekFrom::Start(end)).await?;
+        Ok(items)
+    }
+
+    /// Read a matrix written using `write_matrix` and return the
+    /// number of rows, the number of columns and the row-major
+    /// elements.
+    pub async fn read_matrix<T>(&mut self) -> Result<(usize, usize, Vec<T>)>
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+    {
+        let rows = self.read_u64().await?;
+        let cols = self.read_u64().await?;
+        let overflow = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("matrix dimensions {}x{} overflow", rows, cols),
+            )
+        };
+        let rows = usize::try_from(rows).map_err(|_| overflow())?;
+        let cols = usize::try_from(cols).map_err(|_| overflow())?;
+        let count = rows.checked_mul(cols).ok_or_else(overflow)?;
+        let items = self.read_elements(count).await?;
+        Ok((rows, cols, items))
+    }
+
+    /// Read a sparse array written using `write_sparse` and fill
+    /// the missing entries with the default value.
+    ///
+    /// Errors if an index is out of range or the indices are not
+    /// strictly increasing. Without `max_buffer_size` the length may
+    /// not exceed the number of bytes remaining in the stream.
+    pub async fn read_sparse<T>(&mut self) -> Result<Vec<T>>
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+    {
+        let len = self.read_varint_u64().await?;
+        guard_size!(len, self.options.max_buffer_size);
+        if self.options.max_buffer_size.is_none() {
+            let remaining =
+                self.len().await? - self.stream_position().await?;
+            if len > remaining {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "sparse length {} exceeds remaining length {}",
+                        len, remaining
+                    ),
+                ));
+            }
+        }
+        let count = self.read_varint_u64().await?;
+        if count > len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("sparse count {} exceeds length {}", count, len),
+            ));
+        }
+        let mut items = Vec::new();
+        let mut previous = None;
+        for _ in 0..count {
+            let index = self.read_varint_u64().await?;
+            if index >= len || previous.is_some_and(|p| index <= p) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid sparse index {}", index),
+                ));
+            }
+            previous = Some(index);
+            items.resize_with(index as usize, T::default);
+            let mut item = T::default();
+            item.decode(&mut *self).await?;
+            items.push(item);
+        }
+        items.resize_with(len as usize, T::default);
+        Ok(items)
+    }
+
+    /// Decode records until the predicate rejects a record or
+    /// the end of the stream is reached.
+    ///
+    /// The stream is positioned before the rejected record. A record
+    /// that decodes without reading any bytes is an error.
+    pub async fn read_while<T, F>(&mut self, mut pred: F) -> Result<Vec<T>>
+    where
+        R: Send,
+        T: Decodable + Default + Send,
+        F: FnMut(&T) -> bool + Send,
+    {
+        let length = self.len().await?;
+        let mut items = Vec::new();
+        loop {
+            let position = self.stream_position().await?;
+            if position >= length {
+                break;
+            }
+            let mut item = T::default();
+            item.decode(&mut *self).await?;
+            if self.stream_position().await? == position {
+                return Err(no_progress(position));
+            }
+            if !pred(&item) {
+                self.seek(SeekFrom::Start(position)).await?;
+                break;
+            }
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read bytes one at a time until the predicate rejects the
+    /// bytes read so far or the end of the stream is reached.
+    ///
+    /// The byte that caused the predicate to reject the bytes is
+    /// not included, the stream is positioned before it and it is
+    /// not charged to the byte budget.
+    pub async fn read_while_bytes<F>(
+        &mut self,
+        mut pred: F,
+    ) -> Result<Vec<u8>>
+    where
+        R: Send,
+        F: FnMut(&[u8]) -> bool + Send,
+    {
+        let length = self.len().await?;
+        let mut buffer = Vec::new();
+        while self.stream_position().await? < length {
+            let (budget, consumed) = (self.budget, self.consumed);
+            buffer.push(self.read_u8().await?);
+            if !pred(&buffer) {
+                buffer.pop();
+                self.budget = budget;
+                self.consumed = consumed;
+                self.seek(SeekFrom::Current(-1)).await?;
+                break;
+            }
+            guard_size!(buffer.len(), self.options.max_buffer_size);
+        }
+        Ok(buffer)
+    }
+
+    /// Read optional bytes written using `write_optional_bytes`.
+    pub async fn read_optional_bytes(&mut self) -> Result<Option<Vec<u8>>> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64().await?
+        } else {
+            self.read_u32().await? as u64
+        };
+        if length == optional_sentinel() {
+            return Ok(None);
+        }
+        Ok(Some(self.read_buffer(length as usize).await?))
+    }
+
+    /// Stream of records decoded from the current position
+    /// until the end of the stream.
+    ///
+    /// If a record fails to decode or decodes without reading
+    /// any bytes the error is yielded and the stream ends.
+    pub fn stream<T>(&mut self) -> impl Stream<Item = Result<T>> + '_
+    where
+        T: Decodable + Default + Send + Sync,
+        R: Send,
+    {
+        stream::unfold((self, false), |(reader, done)| async move {
+            if done {
+                return None;
+            }
+            match reader.next_record::<T>().await {
+                Ok(Some(record)) => Some((Ok(record), (reader, false))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), (reader, true))),
+            }
+        })
+    }
+
+    /// Decode the next record or `None` at the end of the stream.
+    async fn next_record<T>(&mut self) -> Result<Option<T>>
+    where
+        T: Decodable + Default + Send + Sync,
+        R: Send,
+    {
+        let position = self.stream_position().await?;
+        if position >= self.len().await? {
+            return Ok(None);
+        }
+        let mut record = T::default();
+        record.decode(&mut *self).await?;
+        if self.stream_position().await? == position {
+            return Err(no_progress(position));
+        }
+        Ok(Some(record))
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> BinaryReader<SubStream<S>> {
+    /// Move the parent stream to the end of the bounded range
+    /// and consume this reader.
+    pub async fn finish(mut self) -> Result<()> {
+        self.stream.seek(SeekFrom::End(0)).await?;
+        Ok(())
+    }
+}
+
+/// Reader borrowed from another reader by `scoped` or `take`.
+///
+/// Dereferences to a `BinaryReader` whose byte budget is capped by
+/// the remaining budget of the parent and deducts the bytes it read
+/// from the parent budget when dropped, including bytes read before
+/// a call to `reset` or `restore`.
+pub struct ChildReader<'a, S>
+where
+    S: AsyncRead + AsyncSeek + Unpin,
+{
+    reader: BinaryReader<S>,
+    parent_budget: Option<&'a mut u64>,
+    parent_consumed: &'a mut u64,
+}
+
+impl<'a, S: AsyncRead + AsyncSeek + Unpin> ChildReader<'a, S> {
+    fn new(
+        stream: S,
+        options: Options,
+        parent_budget: Option<&'a mut u64>,
+        parent_consumed: &'a mut u64,
+        budget: Option<u64>,
+    ) -> Self {
+        let mut reader = BinaryReader::new(stream, options);
+        reader.budget = budget;
+        reader.initial_budget = budget;
+        Self {
+            reader,
+            parent_budget,
+            parent_consumed,
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> ChildReader<'_, SubStream<S>> {
+    /// Move the parent stream to the end of the bounded range
+    /// and consume this reader.
+    pub async fn finish(mut self) -> Result<()> {
+        self.reader.stream.seek(SeekFrom::End(0)).await?;
+        Ok(())
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> Deref for ChildReader<'_, S> {
+    type Target = BinaryReader<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> DerefMut for ChildReader<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> Drop for ChildReader<'_, S> {
+    fn drop(&mut self) {
+        let consumed = self.reader.consumed;
+        if let Some(parent) = self.parent_budget.as_mut() {
+            **parent = parent.saturating_sub(consumed);
+        }
+        *self.parent_consumed = self.parent_consumed.saturating_add(consumed);
+    }
+}
+
+/// Streams that can be truncated or extended to a length.
+#[async_trait]
+pub trait SetLen {
+    /// Set the length of the stream.
+    ///
+    /// Extending the stream fills the new bytes with zeros and the
+    /// position is not changed.
+    async fn set_len(&mut self, len: u64) -> Result<()>;
+}
+
+#[async_trait]
+impl SetLen for Cursor<Vec<u8>> {
+    async fn set_len(&mut self, len: u64) -> Result<()> {
+        self.get_mut().resize(cursor_len(len)?, 0);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SetLen for Cursor<&mut Vec<u8>> {
+    async fn set_len(&mut self, len: u64) -> Result<()> {
+        self.get_mut().resize(cursor_len(len)?, 0);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + SetLen + Unpin + Send> SetLen for BufWriter<W> {
+    async fn set_len(&mut self, len: u64) -> Result<()> {
+        self.flush().await?;
+        self.get_mut().set_len(len).await
+    }
+}
+
+#[async_trait]
+impl<S: SetLen + Send + ?Sized> SetLen for &mut S {
+    async fn set_len(&mut self, len: u64) -> Result<()> {
+        (**self).set_len(len).await
+    }
+}
+
+/// Convert a length for an in-memory buffer.
+fn cursor_len(len: u64) -> Result<usize> {
+    usize::try_from(len).map_err(|_| {
+        Error::new(ErrorKind::InvalidInput, "length overflows usize")
+    })
 }
 
 /// Write to a stream.
@@ -244,22 +1327,52 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
         Self { stream, options }
     }
 
+    /// Seek to the start of the stream and replace the options
+    /// so the writer can be reused.
+    ///
+    /// The stream is not truncated so subsequent writes overwrite
+    /// any existing data; use `reset_truncate` to discard it.
+    pub async fn reset(&mut self, options: Options) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(0)).await?;
+        self.options = options;
+        Ok(())
+    }
+
     /// Seek to a position.
     pub async fn seek(&mut self, to: SeekFrom) -> Result<u64> {
-        Ok(self.stream.seek(to).await?)
+        self.stream.seek(to).await
     }
 
     /// Get the current position.
     pub async fn stream_position(&mut self) -> Result<u64> {
-        Ok(self.stream.stream_position().await?)
+        self.stream.stream_position().await
     }
 
     /// Get the length of this stream by seeking to the end
     /// and then restoring the previous cursor position.
+    #[allow(clippy::len_without_is_empty)]
     pub async fn len(&mut self) -> Result<u64> {
         stream_length(&mut self.stream).await
     }
 
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &W {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading, writing or seeking the stream directly bypasses
+    /// the options for this writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.stream
+    }
+
+    /// Consume this writer and return the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
     /// Write a length-prefixed `String` to the stream.
     pub async fn write_string<S: AsRef<str>>(
         &mut self,
@@ -272,7 +1385,66 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
         } else {
             self.write_u32(bytes.len() as u32).await?;
         }
-        Ok(self.stream.write(bytes).await?)
+        let written = self.stream.write(bytes).await?;
+        Ok(written + self.write_padding().await?)
+    }
+
+    /// Write a string padded with NUL bytes to exactly `len` bytes.
+    ///
+    /// Errors if the string is longer than `len` bytes.
+    pub async fn write_string_fixed<S: AsRef<str>>(
+        &mut self,
+        value: S,
+        len: usize,
+    ) -> Result<usize> {
+        self.write_record_field(value.as_ref().as_bytes(), len)
+            .await
+    }
+
+    /// Write a length-prefixed `String` to the stream and
+    /// error if it contains more than `max_chars` characters.
+    pub async fn write_string_max_chars<S: AsRef<str>>(
+        &mut self,
+        value: S,
+        max_chars: usize,
+    ) -> Result<usize> {
+        guard_chars!(value.as_ref(), max_chars);
+        self.write_string(value).await
+    }
+
+    /// Write a path as a length-prefixed UTF-8 string.
+    ///
+    /// Errors if the path is not valid UTF-8. When the
+    /// `portable_paths` option is set the platform separator is
+    /// written as a forward slash.
+    pub async fn write_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<usize> {
+        let value = encode_path(path.as_ref(), self.options.portable_paths)?;
+        self.write_string(value).await
+    }
+
+    /// Write a UTF-16 string followed by a zero code unit.
+    ///
+    /// Errors if the string contains a NUL character.
+    pub async fn write_cstring_utf16<S: AsRef<str>>(
+        &mut self,
+        value: S,
+    ) -> Result<usize> {
+        let value = value.as_ref();
+        if value.contains('\0') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "string must not contain a nul character",
+            ));
+        }
+        guard_size!(value.len() * 2, self.options.max_buffer_size);
+        let mut written = 0;
+        for unit in value.encode_utf16() {
+            written += self.write_u16(unit).await?;
+        }
+        Ok(written + self.write_u16(0).await?)
     }
 
     /// Write a character to the stream.
@@ -280,7 +1452,30 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
         &mut self,
         v: V,
     ) -> Result<usize> {
-        self.write_u32(*v.borrow() as u32).await
+        let value = *v.borrow() as u32;
+        let data = match self.options.char_endian() {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+            Endian::Native => value.to_ne_bytes(),
+        };
+        self.stream.write(&data).await
+    }
+
+    /// Write a presence flag.
+    ///
+    /// Errors if the reason for an absent value is zero as zero
+    /// indicates a value is present.
+    pub async fn write_presence(
+        &mut self,
+        presence: Presence,
+    ) -> Result<usize> {
+        match presence {
+            Presence::Present => self.write_u8(0).await,
+            Presence::Absent(0) => {
+                Err(Error::other("absent reason must not be zero"))
+            }
+            Presence::Absent(reason) => self.write_u8(reason).await,
+        }
     }
 
     /// Write a `bool` to the stream.
@@ -294,6 +1489,10 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
     }
 
     /// Write a `f32` to the stream.
+    ///
+    /// The bits of the value are copied without any floating point
+    /// operations so subnormals and NaN payloads are preserved
+    /// exactly regardless of the FPU mode.
     pub async fn write_f32<V: Borrow<f32>>(
         &mut self,
         value: V,
@@ -302,6 +1501,10 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
     }
 
     /// Write a `f64` to the stream.
+    ///
+    /// The bits of the value are copied without any floating point
+    /// operations so subnormals and NaN payloads are preserved
+    /// exactly regardless of the FPU mode.
     pub async fn write_f64<V: Borrow<f64>>(
         &mut self,
         value: V,
@@ -405,13 +1608,401 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
         encode_endian!(self.options.endian, value.borrow(), self.stream);
     }
 
+    /// Write a `u64` using unsigned LEB128 variable-length encoding.
+    ///
+    /// The encoding is byte-order independent so the endian
+    /// in the options is ignored.
+    pub async fn write_varint_u64<V: Borrow<u64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let (buffer, length) = encode_varint(*value.borrow());
+        self.stream.write_all(&buffer[..length]).await?;
+        Ok(length)
+    }
+
+    /// Write an `i64` using signed LEB128 and return the number
+    /// of bytes written.
+    ///
+    /// Unlike zig-zag encoding which interleaves negative and positive
+    /// values the groups are written in two's complement and the
+    /// sign is extended from the high bit of the final group, so
+    /// `-1` is `0x7f` and `64` needs two bytes.
+    pub async fn write_varint_i64_signext<V: Borrow<i64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let (buffer, length) = encode_varint_signext(*value.borrow());
+        self.stream.write_all(&buffer[..length]).await?;
+        Ok(length)
+    }
+
+    /// Write an `i64` using zig-zag encoding followed by unsigned
+    /// LEB128 and return the number of bytes written.
+    ///
+    /// Zig-zag maps values of small magnitude to small unsigned
+    /// values so `-1` is `0x01` and `1` is `0x02`.
+    pub async fn write_varint_i64<V: Borrow<i64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let value = *value.borrow();
+        self.write_varint_u64(((value << 1) ^ (value >> 63)) as u64)
+            .await
+    }
+
+    /// Write a list of strings as a varint count followed by the
+    /// varint length of the prefix shared with the previous string
+    /// and the remaining suffix as a length-prefixed string.
+    ///
+    /// Returns the number of bytes written; sorted lists share the
+    /// longest prefixes so compress best.
+    pub async fn write_string_list_prefix_compressed(
+        &mut self,
+        sorted: &[&str],
+    ) -> Result<usize> {
+        let start = self.stream_position().await?;
+        self.write_varint_u64(sorted.len() as u64).await?;
+        let mut previous = "";
+        for value in sorted {
+            let mut shared = previous
+                .bytes()
+                .zip(value.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !value.is_char_boundary(shared) {
+                shared -= 1;
+            }
+            self.write_varint_u64(shared as u64).await?;
+            self.write_string(&value[shared..]).await?;
+            previous = value;
+        }
+        Ok((self.stream_position().await? - start) as usize)
+    }
+
+    /// Write a sequence of non-decreasing `u64` values as a varint
+    /// count followed by the varint difference from the previous
+    /// value; the first value is written as-is.
+    pub async fn write_delta_varint(
+        &mut self,
+        values: &[u64],
+    ) -> Result<usize> {
+        guard_size!(values.len(), self.options.max_buffer_size);
+        let mut written = self.write_varint_u64(values.len() as u64).await?;
+        let mut previous = 0;
+        for value in values {
+            written +=
+                self.write_varint_u64(delta_sub(previous, *value)?).await?;
+            previous = *value;
+        }
+        Ok(written)
+    }
+
     /// Write a byte buffer to the stream.
     pub async fn write_bytes<B: AsRef<[u8]>>(
         &mut self,
         data: B,
     ) -> Result<usize> {
         guard_size!(data.as_ref().len(), self.options.max_buffer_size);
-        Ok(self.stream.write(data.as_ref()).await?)
+        let written = self.stream.write(data.as_ref()).await?;
+        Ok(written + self.write_padding().await?)
+    }
+
+    /// Write zero padding up to the field alignment.
+    async fn write_padding(&mut self) -> Result<usize> {
+        match self.options.field_alignment {
+            Some(alignment) => {
+                let position = self.stream_position().await?;
+                let padding = field_padding(position, alignment) as usize;
+                self.write_padding_with(0, padding).await
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Write `n` copies of a fill byte.
+    pub async fn write_padding_with(
+        &mut self,
+        byte: u8,
+        n: usize,
+    ) -> Result<usize> {
+        if n > 0 {
+            self.stream.write_all(&vec![byte; n]).await?;
+        }
+        Ok(n)
+    }
+
+    /// Write bytes padded with NUL bytes to exactly `width` bytes,
+    /// for example a fixed width field in a tar header.
+    ///
+    /// Errors if the bytes are longer than `width`.
+    pub async fn write_record_field(
+        &mut self,
+        bytes: &[u8],
+        width: usize,
+    ) -> Result<usize> {
+        if bytes.len() > width {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "field length {} exceeds width {}",
+                    bytes.len(),
+                    width
+                ),
+            ));
+        }
+        self.stream.write_all(bytes).await?;
+        self.write_padding_with(0, width - bytes.len()).await?;
+        Ok(width)
+    }
+
+    /// Write zero padding until the position is a multiple of
+    /// `alignment` and return the number of padding bytes written.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub async fn align_to(&mut self, alignment: u64) -> Result<u64> {
+        self.align_to_with(0, alignment).await
+    }
+
+    /// Write copies of a fill byte until the position is a multiple
+    /// of `alignment` and return the number of padding bytes written.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub async fn align_to_with(
+        &mut self,
+        byte: u8,
+        alignment: u64,
+    ) -> Result<u64> {
+        check_alignment(alignment)?;
+        let position = self.stream_position().await?;
+        let padding = field_padding(position, alignment);
+        self.write_padding_with(byte, padding as usize).await?;
+        Ok(padding)
+    }
+
+    /// Write bytes as run-length encoded `[count:u8][value:u8]` pairs.
+    ///
+    /// The decoded length is not written so it must be stored
+    /// separately to read the bytes using `read_rle`.
+    pub async fn write_rle(&mut self, data: &[u8]) -> Result<usize> {
+        let mut written = 0;
+        for run in data.chunk_by(|a, b| a == b) {
+            for chunk in run.chunks(u8::MAX as usize) {
+                self.stream
+                    .write_all(&[chunk.len() as u8, chunk[0]])
+                    .await?;
+                written += 2;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Encode a value and return the range of stream positions
+    /// it occupies.
+    pub async fn write_tracked<T>(
+        &mut self,
+        encodable: &T,
+    ) -> Result<Range<u64>>
+    where
+        W: Send,
+        T: Encodable + Sync,
+    {
+        let start = self.stream_position().await?;
+        encodable.encode(&mut *self).await?;
+        Ok(start..self.stream_position().await?)
+    }
+
+    /// Write elements without a length prefix and return the
+    /// number of bytes written.
+    ///
+    /// Unlike the implementation for `Vec<T>` the count must be
+    /// stored separately to read the elements using `read_elements`.
+    pub async fn write_elements<T>(&mut self, items: &[T]) -> Result<usize>
+    where
+        W: Send,
+        T: Encodable + Sync,
+    {
+        let start = self.stream_position().await?;
+        for item in items {
+            item.encode(&mut *self).await?;
+        }
+        Ok((self.stream_position().await? - start) as usize)
+    }
+
+    /// Write elements followed by the count so the count can be
+    /// computed in a single forward pass; returns the number of
+    /// bytes written.
+    ///
+    /// The count is a `u32` (or `u64` with the `64bit` feature)
+    /// and the list must be at the end of the stream to be read
+    /// using `read_list_suffix_count`.
+    pub async fn write_list_suffix_count<T>(
+        &mut self,
+        items: &[T],
+    ) -> Result<usize>
+    where
+        W: Send,
+        T: Encodable + Sync,
+    {
+        let mut written = self.write_elements(items).await?;
+        written += if cfg!(feature = "64bit") {
+            self.write_u64(items.len() as u64).await?
+        } else {
+            let len: u32 = items.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    items.len()
+                ))
+            })?;
+            self.write_u32(len).await?
+        };
+        Ok(written)
+    }
+
+    /// Write a matrix as the `u64` number of rows and columns
+    /// followed by the row-major elements and return the number
+    /// of bytes written.
+    pub async fn write_matrix<T>(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        data: &[T],
+    ) -> Result<usize>
+    where
+        W: Send,
+        T: Encodable + Sync,
+    {
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "matrix dimensions {}x{} do not match {} elements",
+                    rows,
+                    cols,
+                    data.len()
+                ),
+            ));
+        }
+        let start = self.stream_position().await?;
+        self.write_u64(rows as u64).await?;
+        self.write_u64(cols as u64).await?;
+        self.write_elements(data).await?;
+        Ok((self.stream_position().await? - start) as usize)
+    }
+
+    /// Write the length of a sparse array, the number of entries
+    /// that are not the default value and the varint encoded index
+    /// and value for each of those entries.
+    ///
+    /// Returns the number of bytes written.
+    pub async fn write_sparse<T>(&mut self, items: &[T]) -> Result<usize>
+    where
+        W: Send,
+        T: Encodable + Default + PartialEq + Sync,
+    {
+        let start = self.stream_position().await?;
+        let default = T::default();
+        let count = items.iter().filter(|item| **item != default).count();
+        self.write_varint_u64(items.len() as u64).await?;
+        self.write_varint_u64(count as u64).await?;
+        for (index, item) in items.iter().enumerate() {
+            if *item != default {
+                self.write_varint_u64(index as u64).await?;
+                item.encode(&mut *self).await?;
+            }
+        }
+        Ok((self.stream_position().await? - start) as usize)
+    }
+
+    /// Write a map as an entry count followed by the key and
+    /// value for each entry sorted by key and return the number
+    /// of bytes written.
+    ///
+    /// Sorting the entries means equal maps always encode to the
+    /// same bytes regardless of insertion order and the bytes
+    /// decode as a `HashMap<K, V>`.
+    pub async fn write_map_canonical<K, V>(
+        &mut self,
+        map: &HashMap<K, V>,
+    ) -> Result<usize>
+    where
+        W: Send,
+        K: Encodable + Ord + Sync,
+        V: Encodable + Sync,
+    {
+        let start = self.stream_position().await?;
+        if cfg!(feature = "64bit") {
+            self.write_u64(map.len() as u64).await?;
+        } else {
+            let len: u32 = map.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    map.len()
+                ))
+            })?;
+            self.write_u32(len).await?;
+        }
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in entries {
+            key.encode(&mut *self).await?;
+            value.encode(&mut *self).await?;
+        }
+        Ok((self.stream_position().await? - start) as usize)
+    }
+
+    /// Write optional length-prefixed bytes to the stream.
+    ///
+    /// `None` is encoded as a length prefix using the maximum value
+    /// of the prefix type so a buffer of that length cannot be written.
+    pub async fn write_optional_bytes(
+        &mut self,
+        value: Option<&[u8]>,
+    ) -> Result<usize> {
+        let length = match value {
+            Some(bytes) => {
+                guard_size!(bytes.len(), self.options.max_buffer_size);
+                guard_sentinel!(bytes.len());
+                bytes.len() as u64
+            }
+            None => optional_sentinel(),
+        };
+        let mut written = if cfg!(feature = "64bit") {
+            self.write_u64(length).await?
+        } else {
+            self.write_u32(length as u32).await?
+        };
+        if let Some(bytes) = value {
+            written += self.stream.write(bytes).await?;
+        }
+        Ok(written)
+    }
+
+    /// Append the total length of the stream as a `u64` so readers
+    /// can locate data relative to the end using
+    /// `read_trailer_length`, then flush the stream.
+    ///
+    /// The length includes the eight bytes of the trailer; when
+    /// `total_len_field` is false no trailer is written. Returns
+    /// the length of the stream.
+    pub async fn finalize_with_trailer(
+        &mut self,
+        total_len_field: bool,
+    ) -> Result<u64> {
+        let mut length = self.stream.seek(SeekFrom::End(0)).await?;
+        if total_len_field {
+            length += 8;
+            self.write_u64(length).await?;
+        }
+        self.flush().await?;
+        Ok(length)
+    }
+
+    /// Append footer magic bytes to the end of the stream.
+    pub async fn write_footer(&mut self, magic: &[u8]) -> Result<usize> {
+        self.stream.seek(SeekFrom::End(0)).await?;
+        self.stream.write_all(magic).await?;
+        Ok(magic.len())
     }
 
     /// Flush the write buffer.
@@ -420,6 +2011,16 @@ impl<W: AsyncWrite + AsyncSeek + Unpin> BinaryWriter<W> {
     }
 }
 
+impl<W: AsyncWrite + AsyncSeek + SetLen + Unpin> BinaryWriter<W> {
+    /// Seek to the start of the stream, truncate it and replace
+    /// the options so the writer can be reused without leaving
+    /// bytes from a longer previous encoding.
+    pub async fn reset_truncate(&mut self, options: Options) -> Result<()> {
+        self.reset(options).await?;
+        self.stream.set_len(0).await
+    }
+}
+
 /// Trait for encoding to binary.
 #[async_trait]
 pub trait Encodable {
@@ -428,6 +2029,25 @@ pub trait Encodable {
         &self,
         writer: &mut BinaryWriter<W>,
     ) -> Result<()>;
+
+    /// Encode self into the binary writer overriding the endian
+    /// of the writer for the duration of the call.
+    async fn encode_with<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+        endian: Option<Endian>,
+    ) -> Result<()>
+    where
+        Self: Sync,
+    {
+        let Some(endian) = endian else {
+            return self.encode(writer).await;
+        };
+        let previous = std::mem::replace(&mut writer.options.endian, endian);
+        let result = self.encode(&mut *writer).await;
+        writer.options.endian = previous;
+        result
+    }
 }
 
 /// Trait for decoding from binary.
@@ -438,6 +2058,25 @@ pub trait Decodable {
         &mut self,
         reader: &mut BinaryReader<R>,
     ) -> Result<()>;
+
+    /// Decode from the binary reader into self overriding the endian
+    /// of the reader for the duration of the call.
+    async fn decode_with<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+        endian: Option<Endian>,
+    ) -> Result<()>
+    where
+        Self: Send,
+    {
+        let Some(endian) = endian else {
+            return self.decode(reader).await;
+        };
+        let previous = std::mem::replace(&mut reader.options.endian, endian);
+        let result = self.decode(&mut *reader).await;
+        reader.options.endian = previous;
+        result
+    }
 }
 
 /// Encode to a binary buffer.
@@ -465,14 +2104,35 @@ pub async fn encode_stream<S>(
     encodable: &impl Encodable,
     stream: &mut S,
     options: Options,
-) -> Result<()>
+) -> Result<()>
+where
+    S: AsyncWrite + AsyncSeek + Send + Sync + Unpin,
+{
+    let mut writer = BinaryWriter::new(stream, options);
+    encodable.encode(&mut writer).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Encode to the end of a stream and return the range of stream
+/// positions the value occupies.
+///
+/// Useful to build a single buffer of many values with an index
+/// of the range for each value.
+pub async fn append_encode<S, T>(
+    encodable: &T,
+    stream: &mut S,
+    options: Options,
+) -> Result<Range<u64>>
 where
     S: AsyncWrite + AsyncSeek + Send + Sync + Unpin,
+    T: Encodable + Sync,
 {
     let mut writer = BinaryWriter::new(stream, options);
-    encodable.encode(&mut writer).await?;
+    writer.seek(SeekFrom::End(0)).await?;
+    let range = writer.write_tracked(encodable).await?;
     writer.flush().await?;
-    Ok(())
+    Ok(range)
 }
 
 /// Decode from a stream.
@@ -534,7 +2194,17 @@ where
         &self,
         writer: &mut BinaryWriter<W>,
     ) -> Result<()> {
-        writer.write_u32(self.len() as u32).await?;
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64).await?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len).await?;
+        }
         for item in self {
             item.encode(&mut *writer).await?;
         }
@@ -551,7 +2221,63 @@ where
         &mut self,
         reader: &mut BinaryReader<R>,
     ) -> Result<()> {
-        let len = reader.read_u32().await?;
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64().await?
+        } else {
+            reader.read_u32().await? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut item = T::default();
+            item.decode(&mut *reader).await?;
+            self.push(item);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Encodable for BinaryHeap<T>
+where
+    T: Encodable + Ord + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64).await?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len).await?;
+        }
+        for item in self {
+            item.encode(&mut *writer).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Decodable for BinaryHeap<T>
+where
+    T: Decodable + Default + Ord + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64().await?
+        } else {
+            reader.read_u32().await? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
         for _ in 0..len {
             let mut item = T::default();
             item.decode(&mut *reader).await?;
@@ -561,6 +2287,207 @@ where
     }
 }
 
+#[async_trait]
+impl<K, V> Encodable for HashMap<K, V>
+where
+    K: Encodable + Send + Sync,
+    V: Encodable + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64).await?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len).await?;
+        }
+        for (key, value) in self {
+            key.encode(&mut *writer).await?;
+            value.encode(&mut *writer).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<K, V> Decodable for HashMap<K, V>
+where
+    K: Decodable + Default + Eq + Hash + Send + Sync,
+    V: Decodable + Default + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64().await?
+        } else {
+            reader.read_u32().await? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut key = K::default();
+            key.decode(&mut *reader).await?;
+            let mut value = V::default();
+            value.decode(&mut *reader).await?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<K, V> Encodable for BTreeMap<K, V>
+where
+    K: Encodable + Send + Sync,
+    V: Encodable + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64).await?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len).await?;
+        }
+        for (key, value) in self {
+            key.encode(&mut *writer).await?;
+            value.encode(&mut *writer).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<K, V> Decodable for BTreeMap<K, V>
+where
+    K: Decodable + Default + Ord + Send + Sync,
+    V: Decodable + Default + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64().await?
+        } else {
+            reader.read_u32().await? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut key = K::default();
+            key.decode(&mut *reader).await?;
+            let mut value = V::default();
+            value.decode(&mut *reader).await?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Encodable for Range<T>
+where
+    T: Encodable + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.start.encode(&mut *writer).await?;
+        self.end.encode(&mut *writer).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Decodable for Range<T>
+where
+    T: Decodable + Default + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        self.start.decode(&mut *reader).await?;
+        self.end.decode(&mut *reader).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Encodable for RangeInclusive<T>
+where
+    T: Encodable + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.start().encode(&mut *writer).await?;
+        self.end().encode(&mut *writer).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Decodable for RangeInclusive<T>
+where
+    T: Decodable + Default + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let mut start = T::default();
+        start.decode(&mut *reader).await?;
+        let mut end = T::default();
+        end.decode(&mut *reader).await?;
+        *self = start..=end;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> Encodable for Wrapping<T>
+where
+    T: Encodable + Send + Sync,
+{
+    async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.0.encode(writer).await
+    }
+}
+
+#[async_trait]
+impl<T> Decodable for Wrapping<T>
+where
+    T: Decodable + Send + Sync,
+{
+    async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        self.0.decode(reader).await
+    }
+}
+
 macro_rules! impl_encode_decode {
     ($type:ty, $read:ident, $write:ident) => {
         #[async_trait]
@@ -607,8 +2534,57 @@ impl_encode_decode!(f64, read_f64, write_f64);
 impl_encode_decode!(bool, read_bool, write_bool);
 impl_encode_decode!(char, read_char, write_char);
 impl_encode_decode!(String, read_string, write_string);
+impl_encode_decode!(PathBuf, read_path, write_path);
+
+/// Implement `Encodable` and `Decodable` for a tuple which encodes
+/// each element in order without a length prefix.
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+) => {
+        #[async_trait]
+        impl<$($name),+> Encodable for ($($name,)+)
+        where
+            $($name: Encodable + Send + Sync,)+
+        {
+            async fn encode<W: AsyncWrite + AsyncSeek + Unpin + Send>(
+                &self,
+                writer: &mut BinaryWriter<W>,
+            ) -> Result<()> {
+                $(self.$index.encode(&mut *writer).await?;)+
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl<$($name),+> Decodable for ($($name,)+)
+        where
+            $($name: Decodable + Default + Send + Sync,)+
+        {
+            async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+                &mut self,
+                reader: &mut BinaryReader<R>,
+            ) -> Result<()> {
+                $(self.$index.decode(&mut *reader).await?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod test {
     use super::{BinaryReader, BinaryWriter, Decodable, Encodable};
     use anyhow::Result;
@@ -617,6 +2593,7 @@ mod test {
     use futures::io::{
         AsyncRead, AsyncSeek, AsyncWrite, BufReader, BufWriter, Cursor,
     };
+    use futures::{StreamExt, TryStreamExt};
     #[cfg(feature = "tokio")]
     use std::io::Cursor;
     use std::io::{self, SeekFrom};
@@ -744,6 +2721,125 @@ mod test {
         Ok(())
     }
 
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_read_timeout() -> Result<()> {
+        use std::{
+            pin::Pin,
+            task::{Context, Poll},
+            time::Duration,
+        };
+        use tokio::io::ReadBuf;
+
+        /// Stream that sends some bytes then stalls forever.
+        struct Stalled(Cursor<Vec<u8>>);
+
+        impl AsyncRead for Stalled {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                if self.0.position() == self.0.get_ref().len() as u64 {
+                    return Poll::Pending;
+                }
+                Pin::new(&mut self.0).poll_read(cx, buf)
+            }
+        }
+
+        impl AsyncSeek for Stalled {
+            fn start_seek(
+                mut self: Pin<&mut Self>,
+                position: SeekFrom,
+            ) -> io::Result<()> {
+                Pin::new(&mut self.0).start_seek(position)
+            }
+
+            fn poll_complete(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<u64>> {
+                Pin::new(&mut self.0).poll_complete(cx)
+            }
+        }
+
+        let stream = Stalled(Cursor::new(vec![1, 0]));
+        let mut reader = BinaryReader::with_timeout(
+            stream,
+            Default::default(),
+            Duration::from_millis(20),
+        );
+        assert_eq!(1, reader.read_u16().await?);
+        let error = reader.read_u16().await.unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, error.kind());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_child_budget() -> Result<()> {
+        let mut stream = Cursor::new(vec![0u8; 16]);
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            6,
+        );
+
+        // Children are capped by and charged to the parent budget
+        let mut limited = reader.take(8);
+        limited.read_u32().await?;
+        let error = limited.read_u32().await.unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+        drop(limited);
+        let mut frame = reader.scoped(8).await?;
+        assert!(frame.read_u32().await.is_err());
+        frame.read_u16().await?;
+        frame.finish().await?;
+        assert!(reader.read_u8().await.is_err());
+
+        // Bytes read before a reset, including by a nested child, are
+        // still charged to the parent
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            6,
+        );
+        let mut limited = reader.take(4);
+        let mut inner = limited.take(4);
+        inner.read_u32().await?;
+        drop(inner);
+        limited.reset(Default::default()).await?;
+        limited.read_u16().await?;
+        drop(limited);
+        assert!(reader.read_u8().await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_scoped_frame() -> Result<()> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&5u32.to_le_bytes());
+        buffer.extend_from_slice(&[1, 0, 2, 0, 0xff, 9]);
+
+        let mut stream = Cursor::new(buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let len = reader.read_u32().await? as u64;
+        let mut frame = reader.scoped(len).await?;
+        assert_eq!(1, frame.read_u16().await?);
+        assert_eq!(2, frame.read_u16().await?);
+        assert!(frame.read_u16().await.is_err());
+        frame.finish().await?;
+        assert_eq!(4 + len, reader.stream_position().await?);
+
+        reader.seek(SeekFrom::Start(4)).await?;
+        let mut limited = reader.take(3);
+        assert_eq!(1, limited.read_u16().await?);
+        assert!(limited.read_u16().await.is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn async_tokio_file() -> Result<()> {
         let mock_str = "mock value".to_string();
@@ -987,6 +3083,70 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn async_writer_reset_truncate() -> Result<()> {
+        let mut stream = BufWriter::new(Cursor::new(Vec::new()));
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string("longer").await?;
+        writer.reset_truncate(Default::default()).await?;
+        writer.write_string("short").await?;
+        writer.flush().await?;
+        let expected =
+            crate::encode(&String::from("short"), Default::default())?;
+        assert_eq!(expected, stream.into_inner().into_inner());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_stream_records() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = BufWriter::new(Cursor::new(&mut buffer));
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        for i in 0..5u32 {
+            i.encode(&mut writer).await?;
+        }
+        writer.flush().await?;
+
+        let mut stream = BufReader::new(Cursor::new(&mut buffer));
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let records: Vec<u32> = reader.stream::<u32>().try_collect().await?;
+        assert_eq!(vec![0, 1, 2, 3, 4], records);
+
+        // Truncated record yields an error
+        buffer.pop();
+        let mut stream = BufReader::new(Cursor::new(&mut buffer));
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let records: Vec<io::Result<u32>> =
+            reader.stream::<u32>().collect().await;
+        assert_eq!(5, records.len());
+        assert!(records[4].is_err());
+
+        // Records that read no bytes end the stream with an error
+        #[derive(Debug, Default)]
+        struct Empty;
+
+        #[async_trait]
+        impl Decodable for Empty {
+            async fn decode<R: AsyncRead + AsyncSeek + Unpin + Send>(
+                &mut self,
+                _reader: &mut BinaryReader<R>,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut stream = Cursor::new(&mut buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let records: Vec<io::Result<Empty>> =
+            reader.stream::<Empty>().collect().await;
+        assert_eq!(1, records.len());
+        let error = records.into_iter().next().unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        Ok(())
+    }
+
     // Tests encoding and decoding using the blanket implementation
     // for Vec.
     #[tokio::test]
@@ -6,29 +6,137 @@
 //! Strings are length prefixed using `u32` by default, use
 //! the `64bit` feature if you really need huge strings.
 //!
+//! Enums using the [num_enum](https://docs.rs/num_enum) derive
+//! macros can be read and written using the `num_enum` feature and
+//! the `bitvec` feature adds implementations for
+//! [BitVec](https://docs.rs/bitvec/latest/bitvec/vec/struct.BitVec.html).
+//!
+//! Strings in legacy encodings such as Shift-JIS can be read and
+//! written using [encoding_rs](https://docs.rs/encoding_rs) with
+//! the `encoding` feature.
+//!
+//! The `bytemuck` feature enables zero-copy reads of typed slices
+//! from readers over byte slices.
+//!
+//! Streams can be encrypted using AES-128 in CTR mode with the
+//! `encrypt` feature.
+//!
+//! Fixed width [BigUint](https://docs.rs/num-bigint) values can
+//! be read and written using the `num-bigint` feature.
+//!
+//! The `either` feature adds implementations for
+//! [Either](https://docs.rs/either) written as a `u8` tag of `0`
+//! for `Left` or `1` for `Right` followed by the value.
+//!
+//! The `metrics` feature adds `CountingReader` and `CountingWriter`
+//! which count the operations on a stream when profiling.
+//!
+//! The `derive` feature adds `#[derive(Encodable, Decodable)]` for
+//! structs which encodes the fields in declaration order, fields
+//! marked `#[binary(skip)]` are not encoded and are set to the
+//! default value when decoding.
+//! Add `#[binary(option_bitmap)]` to a struct to write the presence
+//! of the `Option` fields as a leading bitmap rather than a `bool`
+//! before each field.
+//!
+//! Exact [Decimal](https://docs.rs/rust_decimal) values can be
+//! read and written as an `i128` mantissa and a `u8` scale using the
+//! `decimal` feature.
+//!
+//! Enable the `diagnostics` feature so decode errors include the
+//! offset and a hex dump of the surrounding bytes.
+//!
 //! Encode and decode implementations are provided for all primitive
-//! types and blanket implementations for `Option<T>` and `Vec<T>`;
-//! the blank implementation for `Vec<T>` is length prefixed using a
-//! `u32` so will panic if it is longer than `u32::MAX`.
+//! types, `PathBuf` and blanket implementations for `Option<T>`,
+//! `Vec<T>`, `BinaryHeap<T>`, `HashMap<K, V>`, `BTreeMap<K, V>`,
+//! `Range<T>`, `RangeInclusive<T>`, `Wrapping<T>` and tuples of up
+//! to twelve elements; the blanket implementation for `Vec<T>` is
+//! length prefixed using a `u32` (or `u64` with the `64bit`
+//! feature) and decoding errors if the length exceeds
+//! `max_buffer_size`.
+//!
+//! A `BinaryHeap<T>` is written in the internal order of the heap
+//! so only the elements are preserved, not their order.
+//!
+//! Maps are written as an entry count prefixed like `Vec<T>`
+//! followed by each key and value; a `BTreeMap<K, V>` is written in
+//! key order whereas a `HashMap<K, V>` is written in iteration order.
 #![deny(missing_docs)]
 use std::{
+    any::Any,
     borrow::Borrow,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::Display,
+    hash::Hash,
     io::{
         BufReader, BufWriter, Cursor, Error, ErrorKind, Read, Result, Seek,
         SeekFrom, Write,
     },
+    num::Wrapping,
+    ops::{Deref, DerefMut, Range, RangeInclusive},
+    path::{Path, PathBuf, MAIN_SEPARATOR},
 };
 
+mod backing;
+#[cfg(feature = "num-bigint")]
+mod bigint;
+#[cfg(feature = "bitvec")]
+mod bitset;
+mod checksum;
+mod crc32;
+#[cfg(feature = "decimal")]
+mod decimal;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod durable;
+#[cfg(feature = "either")]
+mod either;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "encrypt")]
+mod encrypt;
+#[cfg(feature = "num_enum")]
+mod enums;
+mod fixed;
 #[cfg(feature = "async")]
 pub mod futures;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod packer;
+mod seekable;
+mod substream;
+mod variant;
+
+#[cfg(feature = "derive")]
+extern crate self as binary_stream;
+
+pub use backing::BackingSlice;
+#[cfg(feature = "derive")]
+pub use binary_stream_derive::{Decodable, Encodable};
+pub use checksum::{ChecksumReader, ChecksumWriter};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::DecodeError;
+pub use durable::{write_atomic, SetLen, SyncStream};
+#[cfg(feature = "encrypt")]
+pub use encrypt::{CipherReader, CipherWriter};
+#[cfg(feature = "metrics")]
+pub use metrics::{CountingReader, CountingWriter, Stats};
+pub use packer::{BytePacker, ByteUnpacker};
+pub use seekable::SeekableBuffer;
+pub use substream::SubStream;
+pub use variant::{
+    convert_endian, fourcc, DecodedValue, FieldKind, TagKind, UnknownVariant,
+    VariantTable,
+};
 
 macro_rules! encode_endian {
     ($endian:expr, $value:expr, $stream:expr) => {
         let data = match $endian {
             Endian::Little => $value.to_le_bytes(),
             Endian::Big => $value.to_be_bytes(),
+            Endian::Native => $value.to_ne_bytes(),
         };
-        return Ok($stream.write(&data)?);
+        return $stream.write(&data);
     };
 }
 
@@ -37,6 +145,7 @@ macro_rules! decode_endian {
         let data = match $endian {
             Endian::Little => <$kind>::from_le_bytes($value),
             Endian::Big => <$kind>::from_be_bytes($value),
+            Endian::Native => <$kind>::from_ne_bytes($value),
         };
         return Ok(data);
     };
@@ -46,54 +155,590 @@ macro_rules! guard_size {
     ($len:expr, $max:expr) => {
         if let Some(max) = $max {
             if $len as usize > max {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    format!(
-                        "length {} exceeds max buffer size {}",
-                        $len, max
-                    ),
-                ));
+                return Err(Error::other(format!(
+                    "length {} exceeds max buffer size {}",
+                    $len, max
+                )));
             }
         }
     };
 }
 
+macro_rules! guard_chars {
+    ($value:expr, $max:expr) => {
+        let count = $value.chars().count();
+        if count > $max {
+            return Err(Error::other(format!(
+                "char count {} exceeds max chars {}",
+                count, $max
+            )));
+        }
+    };
+}
+
+macro_rules! guard_sentinel {
+    ($len:expr) => {
+        if $len as u64 >= optional_sentinel() {
+            return Err(Error::other(format!(
+                "length {} collides with the optional sentinel",
+                $len
+            )));
+        }
+    };
+}
+
+macro_rules! guard_chunk_size {
+    ($size:expr) => {
+        if $size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "io chunk size must not be zero",
+            ));
+        }
+    };
+}
+
+macro_rules! read_at {
+    ($($name:ident, $read:ident, $type:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Read a `", stringify!($type),
+                "` at an absolute offset and restore the cursor position."
+            )]
+            pub fn $name(&mut self, offset: u64) -> Result<$type> {
+                self.read_at(offset, |reader| reader.$read())
+            }
+        )*
+    };
+}
+
+macro_rules! read_or {
+    ($($name:ident, $read:ident, $type:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Read a `", stringify!($type),
+                "` or return `default` when the stream is at the end."
+            )]
+            pub fn $name(&mut self, default: $type) -> Result<$type> {
+                if self.stream_position()? == self.len()? {
+                    return Ok(default);
+                }
+                self.$read()
+            }
+        )*
+    };
+}
+
+macro_rules! read_slice {
+    ($($name:ident, $type:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Read `count` values of `", stringify!($type),
+                "` using a single read from the stream."
+            )]
+            pub fn $name(&mut self, count: usize) -> Result<Vec<$type>> {
+                const SIZE: usize = std::mem::size_of::<$type>();
+                let length = count.checked_mul(SIZE).ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidInput, "slice length overflows")
+                })?;
+                guard_size!(length, self.options.max_buffer_size);
+                let mut bytes = vec![0; length];
+                self.fill_buffer(&mut bytes)?;
+                let endian = self.options.endian;
+                Ok(bytes
+                    .chunks_exact(SIZE)
+                    .map(|chunk| {
+                        let chunk: [u8; SIZE] = chunk.try_into().unwrap();
+                        match endian {
+                            Endian::Little => <$type>::from_le_bytes(chunk),
+                            Endian::Big => <$type>::from_be_bytes(chunk),
+                            Endian::Native => {
+                                <$type>::from_ne_bytes(chunk)
+                            }
+                        }
+                    })
+                    .collect())
+            }
+        )*
+    };
+}
+
+macro_rules! read_endian {
+    ($($name:ident, $type:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Read a `", stringify!($type),
+                "` using `endian` instead of the endian for the reader."
+            )]
+            pub fn $name(&mut self, endian: Endian) -> Result<$type> {
+                let mut buffer = [0; std::mem::size_of::<$type>()];
+                self.fill_primitive(&mut buffer)?;
+                decode_endian!(endian, buffer, $type);
+            }
+        )*
+    };
+}
+
+macro_rules! write_endian {
+    ($($name:ident, $type:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Write a `", stringify!($type),
+                "` using `endian` instead of the endian for the writer."
+            )]
+            pub fn $name<V: Borrow<$type>>(
+                &mut self,
+                value: V,
+                endian: Endian,
+            ) -> Result<usize> {
+                encode_endian!(endian, value.borrow(), self.stream);
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "async")]
 pub(crate) use decode_endian;
+#[cfg(feature = "async")]
+pub(crate) use guard_chars;
+#[cfg(feature = "async")]
+pub(crate) use guard_chunk_size;
+#[cfg(feature = "async")]
+pub(crate) use guard_sentinel;
 pub(crate) use guard_size;
 
 /// Variants to describe endianness.
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 pub enum Endian {
     /// Big endian.
     Big,
     /// Little endian.
+    #[default]
     Little,
+    /// Byte order of the target platform.
+    Native,
 }
 
-impl Default for Endian {
-    fn default() -> Self {
-        Self::Little
+impl Endian {
+    /// Byte order of the target platform.
+    pub const fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            Endian::Little
+        } else {
+            Endian::Big
+        }
+    }
+
+    /// Concrete byte order with `Native` resolved for the target.
+    pub const fn resolve(self) -> Self {
+        match self {
+            Endian::Native => Endian::native(),
+            other => other,
+        }
     }
 }
 
+/// Default chunk size for bulk read operations.
+pub const IO_CHUNK_SIZE: usize = 8192;
+
+/// Presence of a value that carries a reason when absent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Presence {
+    /// Value is present.
+    Present,
+    /// Value is absent for the given non-zero reason.
+    Absent(u8),
+}
+
+/// Policy for failed primitive reads.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum ErrorPolicy {
+    /// Return the error.
+    #[default]
+    Fail,
+    /// Return the default value for the type and record the error.
+    DefaultValue,
+}
+
 /// Options for reading and writing.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Options {
     /// The endian type.
     pub endian: Endian,
     /// Maximum buffer size for strings and byte slices.
     pub max_buffer_size: Option<usize>,
+    /// Chunk size for bulk read operations, must not be zero.
+    pub io_chunk_size: usize,
+    /// Alignment in bytes for the end of strings and byte buffers.
+    ///
+    /// Zero padding is written after each field up to the alignment
+    /// and the length prefix stores the unpadded length.
+    pub field_alignment: Option<u64>,
+    /// Endian for `char` code points, when `None` the code points
+    /// use `endian`.
+    ///
+    /// Set this for formats that store code points in a fixed
+    /// byte order regardless of the endian for other values.
+    pub char_endian: Option<Endian>,
+    /// Write paths using forward slashes as the separator so they
+    /// can be read on all platforms, enabled by default.
+    ///
+    /// Only the separator of the current platform is translated so
+    /// a backslash in a Unix file name is preserved.
+    pub portable_paths: bool,
+    /// Maximum length in bytes for length-prefixed strings.
+    ///
+    /// Checked as soon as the prefix is read so an oversized
+    /// prefix errors before the string is allocated; unlike
+    /// `max_buffer_size` this does not apply to byte slices.
+    pub max_string_len: Option<usize>,
+    /// Policy when a primitive read reaches the end of the stream.
+    ///
+    /// Using `ErrorPolicy::DefaultValue` lets tolerant parsers show
+    /// partial data for a truncated record: the read yields zero,
+    /// `false` or `0.0` and the error is available from
+    /// `BinaryReader::last_error`. Use with care, the stream
+    /// position after a truncated read is unspecified, only end of
+    /// stream errors are replaced and strings, byte slices and
+    /// length prefixed values are not covered so a defaulted length
+    /// decodes as empty.
+    pub on_read_error: ErrorPolicy,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            endian: Default::default(),
+            max_buffer_size: None,
+            io_chunk_size: IO_CHUNK_SIZE,
+            field_alignment: None,
+            char_endian: None,
+            portable_paths: true,
+            on_read_error: Default::default(),
+            max_string_len: None,
+        }
+    }
+}
+
+impl Options {
+    /// Create a builder starting from the default options.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+
+    /// Endian used for `char` code points.
+    pub(crate) fn char_endian(&self) -> Endian {
+        self.char_endian.unwrap_or(self.endian)
+    }
+
+    /// Error if a string length exceeds `max_string_len`.
+    pub(crate) fn check_string_len(&self, length: u64) -> Result<()> {
+        if let Some(max) = self.max_string_len {
+            if length > max as u64 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "string length {} exceeds max_string_len {}",
+                        length, max
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for `Options` that is not affected when options are
+/// added, see the fields of `Options` for details of each option.
+#[derive(Clone, Default)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl OptionsBuilder {
+    /// Set the endian.
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.options.endian = endian;
+        self
+    }
+
+    /// Set the maximum buffer size for strings and byte slices.
+    pub fn max_buffer_size(mut self, size: usize) -> Self {
+        self.options.max_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the chunk size for bulk read operations.
+    pub fn io_chunk_size(mut self, size: usize) -> Self {
+        self.options.io_chunk_size = size;
+        self
+    }
+
+    /// Set the alignment for the end of strings and byte buffers.
+    pub fn field_alignment(mut self, alignment: u64) -> Self {
+        self.options.field_alignment = Some(alignment);
+        self
+    }
+
+    /// Set the endian for `char` code points.
+    pub fn char_endian(mut self, endian: Endian) -> Self {
+        self.options.char_endian = Some(endian);
+        self
+    }
+
+    /// Set whether paths are written using forward slashes.
+    pub fn portable_paths(mut self, portable: bool) -> Self {
+        self.options.portable_paths = portable;
+        self
+    }
+
+    /// Set the maximum length in bytes for length-prefixed strings.
+    pub fn max_string_len(mut self, length: usize) -> Self {
+        self.options.max_string_len = Some(length);
+        self
+    }
+
+    /// Set the policy when a primitive read reaches the end of
+    /// the stream.
+    pub fn on_read_error(mut self, policy: ErrorPolicy) -> Self {
+        self.options.on_read_error = policy;
+        self
+    }
+
+    /// Build the options.
+    pub fn build(self) -> Options {
+        self.options
+    }
 }
 
 impl From<Endian> for Options {
     fn from(endian: Endian) -> Self {
         Self {
             endian,
-            max_buffer_size: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// Reader state captured by `BinaryReader::snapshot`.
+#[derive(Clone)]
+pub struct Snapshot {
+    position: u64,
+    options: Options,
+    budget: Option<u64>,
+}
+
+/// Convert a path to a string for encoding.
+pub(crate) fn encode_path(path: &Path, portable: bool) -> Result<String> {
+    let value = path.to_str().ok_or_else(|| {
+        Error::new(ErrorKind::InvalidInput, "path is not valid utf-8")
+    })?;
+    Ok(if portable && MAIN_SEPARATOR != '/' {
+        value.replace(MAIN_SEPARATOR, "/")
+    } else {
+        value.to_owned()
+    })
+}
+
+/// Number of padding bytes needed to reach an alignment.
+pub(crate) fn field_padding(position: u64, alignment: u64) -> u64 {
+    if alignment > 1 {
+        (alignment - position % alignment) % alignment
+    } else {
+        0
+    }
+}
+
+/// Error if an alignment is not a power of two.
+pub(crate) fn check_alignment(alignment: u64) -> Result<()> {
+    if !alignment.is_power_of_two() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("alignment {} is not a power of two", alignment),
+        ));
+    }
+    Ok(())
+}
+
+/// Length prefix that indicates `None` for optional bytes.
+pub(crate) fn optional_sentinel() -> u64 {
+    if cfg!(feature = "64bit") {
+        u64::MAX
+    } else {
+        u32::MAX as u64
+    }
+}
+
+/// Maximum number of bytes in a LEB128 encoded `u64`.
+pub(crate) const VARINT_MAX_LEN: usize = 10;
+
+/// Encode a `u64` as unsigned LEB128 returning the buffer and
+/// the number of bytes used.
+pub(crate) fn encode_varint(mut value: u64) -> ([u8; VARINT_MAX_LEN], usize) {
+    let mut buffer = [0u8; VARINT_MAX_LEN];
+    let mut length = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer[length] = byte;
+            return (buffer, length + 1);
+        }
+        buffer[length] = byte | 0x80;
+        length += 1;
+    }
+}
+
+/// Accumulate the byte at `index` of a LEB128 encoded `u64`
+/// and return whether it is the final byte.
+pub(crate) fn decode_varint(
+    value: &mut u64,
+    index: usize,
+    byte: u8,
+) -> Result<bool> {
+    if index == VARINT_MAX_LEN - 1 && byte > 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "varint overflows u64",
+        ));
+    }
+    *value |= ((byte & 0x7f) as u64) << (7 * index);
+    Ok(byte & 0x80 == 0)
+}
+
+/// Encode an `i64` as signed LEB128 returning the buffer and
+/// the number of bytes used.
+pub(crate) fn encode_varint_signext(
+    mut value: i64,
+) -> ([u8; VARINT_MAX_LEN], usize) {
+    let mut buffer = [0u8; VARINT_MAX_LEN];
+    let mut length = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let sign = byte & 0x40 != 0;
+        if (value == 0 && !sign) || (value == -1 && sign) {
+            buffer[length] = byte;
+            return (buffer, length + 1);
+        }
+        buffer[length] = byte | 0x80;
+        length += 1;
+    }
+}
+
+/// Accumulate the byte at `index` of a signed LEB128 encoded `i64`
+/// and return whether it is the final byte.
+pub(crate) fn decode_varint_signext(
+    value: &mut i64,
+    index: usize,
+    byte: u8,
+) -> Result<bool> {
+    if index == VARINT_MAX_LEN - 1 && byte != 0 && byte != 0x7f {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "varint overflows i64",
+        ));
+    }
+    let shift = 7 * index;
+    *value |= ((byte & 0x7f) as i64) << shift;
+    if byte & 0x80 != 0 {
+        return Ok(false);
+    }
+    if shift + 7 < 64 && byte & 0x40 != 0 {
+        *value |= -1 << (shift + 7);
+    }
+    Ok(true)
+}
+
+/// Error for a varint without a terminating byte.
+pub(crate) fn varint_unterminated() -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("varint exceeds {} bytes", VARINT_MAX_LEN),
+    )
+}
+
+/// Add a delta to the previous value in a delta encoded sequence.
+pub(crate) fn delta_add(previous: u64, delta: u64) -> Result<u64> {
+    previous.checked_add(delta).ok_or_else(|| {
+        Error::new(ErrorKind::InvalidData, "delta overflows u64")
+    })
+}
+
+/// Get the delta between values in a delta encoded sequence.
+pub(crate) fn delta_sub(previous: u64, value: u64) -> Result<u64> {
+    value.checked_sub(previous).ok_or_else(|| {
+        Error::other(format!(
+            "value {} is less than previous value {}",
+            value, previous
+        ))
+    })
+}
+
+/// Validate the first `filled` bytes of a chunk as UTF-8 and move
+/// an incomplete trailing sequence to the start of the chunk.
+///
+/// Returns the length of the incomplete sequence.
+pub(crate) fn utf8_carry(chunk: &mut [u8], filled: usize) -> Result<usize> {
+    match std::str::from_utf8(&chunk[..filled]) {
+        Ok(_) => Ok(0),
+        Err(e) if e.error_len().is_none() => {
+            let valid = e.valid_up_to();
+            chunk.copy_within(valid..filled, 0);
+            Ok(filled - valid)
         }
+        Err(_) => Err(Error::other("invalid utf-8")),
     }
 }
 
+/// Payload of the error for a read that exceeds the byte budget
+/// so it is not mistaken for the end of the stream.
+#[derive(Debug)]
+struct BudgetExceeded {
+    length: u64,
+    remaining: u64,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "length {} exceeds remaining byte budget {}",
+            self.length, self.remaining
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// Error for a read of `length` bytes that exceeds the
+/// remaining byte budget.
+pub(crate) fn budget_exceeded(length: u64, remaining: u64) -> Error {
+    Error::new(
+        ErrorKind::UnexpectedEof,
+        BudgetExceeded { length, remaining },
+    )
+}
+
+/// Determine if an error was caused by an exceeded byte budget.
+pub(crate) fn is_budget_exceeded(error: &Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<BudgetExceeded>())
+}
+
+/// Error for a record at `position` that decoded without reading
+/// any bytes so decoding records until a condition would not end.
+pub(crate) fn no_progress(position: u64) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "record at position {} decoded without reading any bytes",
+            position
+        ),
+    )
+}
+
 /// Get the length of a stream by seeking to the end
 /// and then restoring the previous position.
 pub fn stream_length<S: Seek>(stream: &mut S) -> Result<u64> {
@@ -110,80 +755,461 @@ where
 {
     stream: R,
     options: Options,
+    budget: Option<u64>,
+    initial_budget: Option<u64>,
+    consumed: u64,
+    context: Option<Box<dyn Any + Send + Sync>>,
+    last_error: Option<Error>,
 }
 
 impl<R: Read + Seek> BinaryReader<R> {
     /// Create a binary reader with the given options.
     pub fn new(stream: R, options: Options) -> Self {
-        Self { stream, options }
+        Self {
+            stream,
+            options,
+            budget: None,
+            initial_budget: None,
+            consumed: 0,
+            context: None,
+            last_error: None,
+        }
+    }
+
+    /// Create a binary reader that errors once more than `budget`
+    /// bytes in total have been read from the stream.
+    ///
+    /// Unlike `max_buffer_size` which limits the size of individual
+    /// strings and byte slices the budget caps the total number of
+    /// bytes read across all calls.
+    pub fn with_byte_budget(
+        stream: R,
+        options: Options,
+        budget: u64,
+    ) -> Self {
+        Self {
+            stream,
+            options,
+            budget: Some(budget),
+            initial_budget: Some(budget),
+            consumed: 0,
+            context: None,
+            last_error: None,
+        }
+    }
+
+    /// Read exactly enough bytes to fill the buffer and deduct
+    /// the length of the buffer from the byte budget.
+    fn fill_buffer(&mut self, buffer: &mut [u8]) -> Result<()> {
+        self.charge_budget(buffer.len() as u64)?;
+        self.stream.read_exact(buffer)?;
+        Ok(())
+    }
+
+    /// Fill the buffer for a primitive value.
+    ///
+    /// When the error policy is `DefaultValue` an unexpected end of
+    /// stream zeroes the buffer and records the error; an exceeded
+    /// byte budget is always returned.
+    fn fill_primitive(&mut self, buffer: &mut [u8]) -> Result<()> {
+        match self.fill_buffer(buffer) {
+            Err(e)
+                if e.kind() == ErrorKind::UnexpectedEof
+                    && !is_budget_exceeded(&e)
+                    && self.options.on_read_error
+                        == ErrorPolicy::DefaultValue =>
+            {
+                buffer.fill(0);
+                self.last_error = Some(e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Read up to the length of the buffer and deduct the
+    /// number of bytes read from the byte budget.
+    fn read_chunk(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        loop {
+            match self.stream.read(buffer) {
+                Ok(read) => {
+                    self.charge_budget(read as u64)?;
+                    return Ok(read);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Error if a length exceeds the byte budget so callers can
+    /// check the budget before allocating a buffer.
+    fn check_budget(&self, length: u64) -> Result<()> {
+        match self.budget {
+            Some(remaining) if length > remaining => {
+                Err(budget_exceeded(length, remaining))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Deduct a length from the byte budget.
+    fn charge_budget(&mut self, length: u64) -> Result<()> {
+        self.check_budget(length)?;
+        if let Some(remaining) = self.budget.as_mut() {
+            *remaining -= length;
+        }
+        self.consumed = self.consumed.saturating_add(length);
+        Ok(())
+    }
+
+    /// Seek to the start of the stream, replace the options and
+    /// restore the byte budget so the reader can be reused for
+    /// another pass.
+    pub fn reset(&mut self, options: Options) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(0))?;
+        self.options = options;
+        self.budget = self.initial_budget;
+        self.last_error = None;
+        Ok(())
+    }
+
+    /// Error recorded by the most recent primitive read that
+    /// returned a default value.
+    ///
+    /// Only set when `on_read_error` is `ErrorPolicy::DefaultValue`.
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Get the options for this reader.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Get a mutable reference to the options for this reader.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// Capture the position, options and remaining byte budget
+    /// so a speculative parse can be undone using `restore`.
+    pub fn snapshot(&mut self) -> Result<Snapshot> {
+        Ok(Snapshot {
+            position: self.stream.stream_position()?,
+            options: self.options.clone(),
+            budget: self.budget,
+        })
+    }
+
+    /// Seek to the position of a snapshot and restore the options
+    /// and byte budget.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(snapshot.position))?;
+        self.options = snapshot.options;
+        self.budget = snapshot.budget;
+        Ok(())
     }
 
     /// Seek to a position.
     pub fn seek(&mut self, to: SeekFrom) -> Result<u64> {
-        Ok(self.stream.seek(to)?)
+        self.stream.seek(to)
     }
 
     /// Get the current seek position.
     pub fn stream_position(&mut self) -> Result<u64> {
-        Ok(self.stream.stream_position()?)
+        self.stream.stream_position()
+    }
+
+    /// Seek forward `count` bytes from the current position.
+    pub fn skip(&mut self, count: u64) -> Result<()> {
+        let offset = i64::try_from(count).map_err(|_| {
+            Error::new(ErrorKind::InvalidInput, "skip count overflows")
+        })?;
+        self.stream.seek(SeekFrom::Current(offset))?;
+        Ok(())
+    }
+
+    /// Seek forward over padding until the position is a multiple
+    /// of `alignment` and return the number of padding bytes skipped.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub fn align_to(&mut self, alignment: u64) -> Result<u64> {
+        check_alignment(alignment)?;
+        let position = self.stream.stream_position()?;
+        let padding = field_padding(position, alignment);
+        self.skip(padding)?;
+        Ok(padding)
+    }
+
+    /// Read the next byte without consuming it.
+    ///
+    /// The position and byte budget are restored even if the read
+    /// fails and the end of the stream is always an error.
+    pub fn peek_u8(&mut self) -> Result<u8> {
+        let position = self.stream.stream_position()?;
+        let (budget, consumed) = (self.budget, self.consumed);
+        let mut buffer = [0; 1];
+        let result = self.fill_buffer(&mut buffer);
+        self.budget = budget;
+        self.consumed = consumed;
+        self.stream.seek(SeekFrom::Start(position))?;
+        result.map(|_| buffer[0])
     }
 
     /// Get the length of this stream by seeking to the end
     /// and then restoring the previous cursor position.
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&mut self) -> Result<u64> {
         stream_length(&mut self.stream)
     }
 
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading, writing or seeking the stream directly bypasses
+    /// the options for this reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.stream
+    }
+
+    /// Consume this reader and return the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.stream
+    }
+
+    /// Read the total length written at the end of the stream
+    /// by `finalize_with_trailer` and restore the position.
+    pub fn read_trailer_length(&mut self) -> Result<u64> {
+        let length = self.len()?;
+        if length < 8 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for a trailer",
+            ));
+        }
+        let position = self.stream.stream_position()?;
+        self.stream.seek(SeekFrom::Start(length - 8))?;
+        let trailer = self.read_u64();
+        self.stream.seek(SeekFrom::Start(position))?;
+        trailer
+    }
+
+    /// Compare the bytes at the end of the stream with the footer
+    /// written by `write_footer` and restore the position.
+    ///
+    /// Errors with `InvalidData` if the footer does not match.
+    pub fn read_footer(&mut self, expected: &[u8]) -> Result<()> {
+        let length = self.len()?;
+        if length < expected.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for the footer",
+            ));
+        }
+        let position = self.stream.stream_position()?;
+        self.stream
+            .seek(SeekFrom::Start(length - expected.len() as u64))?;
+        let footer = self.read_buffer(expected.len());
+        self.stream.seek(SeekFrom::Start(position))?;
+        if footer? != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "footer magic does not match",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Set user state that nested decoders can access using
+    /// `context` and `context_mut`, replacing any existing state.
+    ///
+    /// The state is not shared with the readers returned for
+    /// nested blocks such as `read_sized`.
+    pub fn set_context<T: Any + Send + Sync>(&mut self, context: T) {
+        self.context = Some(Box::new(context));
+    }
+
+    /// Get a reference to the user state if it is of type `T`.
+    pub fn context<T: Any>(&self) -> Option<&T> {
+        self.context.as_ref()?.downcast_ref()
+    }
+
+    /// Get a mutable reference to the user state if it is of type `T`.
+    pub fn context_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.context.as_mut()?.downcast_mut()
+    }
+
+    /// Remove the user state and return it if it is of type `T`.
+    pub fn take_context<T: Any>(&mut self) -> Option<T> {
+        match self.context.take()?.downcast() {
+            Ok(context) => Some(*context),
+            Err(context) => {
+                self.context = Some(context);
+                None
+            }
+        }
+    }
+
     /// Read a length-prefixed `String` from the stream.
     pub fn read_string(&mut self) -> Result<String> {
         let chars = if cfg!(feature = "64bit") {
             let str_len = self.read_u64()?;
+            self.options.check_string_len(str_len)?;
             guard_size!(str_len, self.options.max_buffer_size);
+            self.check_budget(str_len)?;
             let mut chars: Vec<u8> = vec![0; str_len as usize];
-            self.stream.read_exact(&mut chars)?;
+            self.fill_buffer(&mut chars)?;
             chars
         } else {
             let str_len = self.read_u32()?;
+            self.options.check_string_len(str_len as u64)?;
             guard_size!(str_len, self.options.max_buffer_size);
+            self.check_budget(str_len as u64)?;
             let mut chars: Vec<u8> = vec![0; str_len as usize];
-            self.stream.read_exact(&mut chars)?;
+            self.fill_buffer(&mut chars)?;
             chars
         };
-        Ok(String::from_utf8(chars)
-            .map_err(|_| Error::new(ErrorKind::Other, "invalid utf-8"))?)
+        self.skip_padding()?;
+        String::from_utf8(chars).map_err(|_| Error::other("invalid utf-8"))
     }
 
-    /// Read a character from the stream.
-    pub fn read_char(&mut self) -> Result<char> {
-        std::char::from_u32(self.read_u32()?)
-            .ok_or_else(|| Error::new(ErrorKind::Other, "invalid character"))
+    /// Read a string from a field of exactly `len` bytes written
+    /// using `write_string_fixed` and trim the trailing NUL bytes.
+    pub fn read_string_fixed(&mut self, len: usize) -> Result<String> {
+        let chars = self.read_record_field(len)?;
+        String::from_utf8(chars).map_err(|_| Error::other("invalid utf-8"))
     }
 
-    /// Read a `bool` from the stream.
-    pub fn read_bool(&mut self) -> Result<bool> {
-        let value = self.read_u8()?;
-        Ok(value > 0)
+    /// Read a length-prefixed string and validate it is UTF-8 in
+    /// chunks without allocating or constructing a `String`.
+    ///
+    /// Returns the number of bytes consumed including the length prefix.
+    pub fn validate_string(&mut self) -> Result<usize> {
+        let (prefix, str_len) = if cfg!(feature = "64bit") {
+            (8, self.read_u64()?)
+        } else {
+            (4, self.read_u32()? as u64)
+        };
+        self.options.check_string_len(str_len)?;
+        guard_size!(str_len, self.options.max_buffer_size);
+        self.check_budget(str_len)?;
+        let mut chunk = [0u8; 1024];
+        let (mut remaining, mut pending) = (str_len, 0);
+        while remaining > 0 {
+            let len = remaining.min((chunk.len() - pending) as u64) as usize;
+            self.fill_buffer(&mut chunk[pending..pending + len])?;
+            remaining -= len as u64;
+            pending = utf8_carry(&mut chunk, pending + len)?;
+        }
+        if pending > 0 {
+            return Err(Error::other("invalid utf-8"));
+        }
+        self.skip_padding()?;
+        Ok(prefix + str_len as usize)
     }
 
-    /// Read a `f32` from the stream.
-    pub fn read_f32(&mut self) -> Result<f32> {
-        let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer)?;
-        decode_endian!(self.options.endian, buffer, f32);
+    /// Read a length-prefixed `String` from the stream and
+    /// error if it contains more than `max_chars` characters.
+    pub fn read_string_max_chars(
+        &mut self,
+        max_chars: usize,
+    ) -> Result<String> {
+        let value = self.read_string()?;
+        guard_chars!(value, max_chars);
+        Ok(value)
     }
 
-    /// Read a `f64` from the stream.
-    pub fn read_f64(&mut self) -> Result<f64> {
-        let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer)?;
-        decode_endian!(self.options.endian, buffer, f64);
+    /// Read a path written using `write_path`.
+    pub fn read_path(&mut self) -> Result<PathBuf> {
+        Ok(PathBuf::from(self.read_string()?))
     }
 
-    /// Read an `isize` from the stream.
+    /// Read a UTF-16 string terminated by a zero code unit.
+    ///
+    /// Code units use the endian for the reader and the
+    /// terminator is consumed but not included in the string.
+    pub fn read_cstring_utf16(&mut self) -> Result<String> {
+        let mut units = Vec::new();
+        loop {
+            let unit = self.read_u16()?;
+            if unit == 0 {
+                break;
+            }
+            units.push(unit);
+            guard_size!(units.len() * 2, self.options.max_buffer_size);
+        }
+        String::from_utf16(&units)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid utf-16"))
+    }
+
+    /// Read a character from the stream.
+    pub fn read_char(&mut self) -> Result<char> {
+        let mut buffer: [u8; 4] = [0; 4];
+        self.fill_buffer(&mut buffer)?;
+        let value = match self.options.char_endian() {
+            Endian::Little => u32::from_le_bytes(buffer),
+            Endian::Big => u32::from_be_bytes(buffer),
+            Endian::Native => u32::from_ne_bytes(buffer),
+        };
+        std::char::from_u32(value)
+            .ok_or_else(|| Error::other("invalid character"))
+    }
+
+    /// Read a presence flag where zero indicates a value is present
+    /// and any other value is the reason the value is absent.
+    pub fn read_presence(&mut self) -> Result<Presence> {
+        Ok(match self.read_u8()? {
+            0 => Presence::Present,
+            reason => Presence::Absent(reason),
+        })
+    }
+
+    /// Read a schema hash and error if it does not match the
+    /// expected hash.
+    pub fn check_schema_hash(&mut self, expected: u32) -> Result<()> {
+        let actual = self.read_u32()?;
+        if actual != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "schema hash {:#010x} does not match {:#010x}",
+                    actual, expected
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read a `bool` from the stream.
+    pub fn read_bool(&mut self) -> Result<bool> {
+        let value = self.read_u8()?;
+        Ok(value > 0)
+    }
+
+    /// Read a `f32` from the stream.
+    pub fn read_f32(&mut self) -> Result<f32> {
+        let mut buffer: [u8; 4] = [0; 4];
+        self.fill_primitive(&mut buffer)?;
+        decode_endian!(self.options.endian, buffer, f32);
+    }
+
+    /// Read a `f64` from the stream.
+    pub fn read_f64(&mut self) -> Result<f64> {
+        let mut buffer: [u8; 8] = [0; 8];
+        self.fill_primitive(&mut buffer)?;
+        decode_endian!(self.options.endian, buffer, f64);
+    }
+
+    /// Read an `isize` from the stream.
     #[cfg(target_pointer_width = "32")]
     pub fn read_isize(&mut self) -> Result<isize> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, isize);
     }
 
@@ -191,7 +1217,7 @@ impl<R: Read + Seek> BinaryReader<R> {
     #[cfg(target_pointer_width = "64")]
     pub fn read_isize(&mut self) -> Result<isize> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, isize);
     }
 
@@ -199,7 +1225,7 @@ impl<R: Read + Seek> BinaryReader<R> {
     #[cfg(target_pointer_width = "32")]
     pub fn read_usize(&mut self) -> Result<usize> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, usize);
     }
 
@@ -207,87 +1233,896 @@ impl<R: Read + Seek> BinaryReader<R> {
     #[cfg(target_pointer_width = "64")]
     pub fn read_usize(&mut self) -> Result<usize> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, usize);
     }
 
     /// Read a `u64` from the stream.
     pub fn read_u64(&mut self) -> Result<u64> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, u64);
     }
 
     /// Read an `i64` from the stream.
     pub fn read_i64(&mut self) -> Result<i64> {
         let mut buffer: [u8; 8] = [0; 8];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, i64);
     }
 
     /// Read a `u128` from the stream.
     pub fn read_u128(&mut self) -> Result<u128> {
         let mut buffer: [u8; 16] = [0; 16];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, u128);
     }
 
     /// Read an `i128` from the stream.
     pub fn read_i128(&mut self) -> Result<i128> {
         let mut buffer: [u8; 16] = [0; 16];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, i128);
     }
 
     /// Read a `u32` from the stream.
     pub fn read_u32(&mut self) -> Result<u32> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, u32);
     }
 
     /// Read an `i32` from the stream.
     pub fn read_i32(&mut self) -> Result<i32> {
         let mut buffer: [u8; 4] = [0; 4];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, i32);
     }
 
     /// Read a `u16` from the stream.
     pub fn read_u16(&mut self) -> Result<u16> {
         let mut buffer: [u8; 2] = [0; 2];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, u16);
     }
 
     /// Read an `i16` from the stream.
     pub fn read_i16(&mut self) -> Result<i16> {
         let mut buffer: [u8; 2] = [0; 2];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, i16);
     }
 
     /// Read a `u8` from the stream.
     pub fn read_u8(&mut self) -> Result<u8> {
         let mut buffer: [u8; 1] = [0; 1];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, u8);
     }
 
     /// Read an `i8` from the stream.
     pub fn read_i8(&mut self) -> Result<i8> {
         let mut buffer: [u8; 1] = [0; 1];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_primitive(&mut buffer)?;
         decode_endian!(self.options.endian, buffer, i8);
     }
 
+    /// Seek to an absolute offset, call the closure and then
+    /// restore the previous cursor position.
+    pub fn read_at<T, F>(&mut self, offset: u64, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let position = self.stream.stream_position()?;
+        self.stream.seek(SeekFrom::Start(offset))?;
+        let result = f(self);
+        self.stream.seek(SeekFrom::Start(position))?;
+        result
+    }
+
+    read_at! {
+        read_u8_at, read_u8, u8;
+        read_i8_at, read_i8, i8;
+        read_u16_at, read_u16, u16;
+        read_i16_at, read_i16, i16;
+        read_u32_at, read_u32, u32;
+        read_i32_at, read_i32, i32;
+        read_u64_at, read_u64, u64;
+        read_i64_at, read_i64, i64;
+        read_u128_at, read_u128, u128;
+        read_i128_at, read_i128, i128;
+        read_f32_at, read_f32, f32;
+        read_f64_at, read_f64, f64;
+    }
+
+    read_endian! {
+        read_u16_endian, u16;
+        read_i16_endian, i16;
+        read_u32_endian, u32;
+        read_i32_endian, i32;
+        read_u64_endian, u64;
+        read_i64_endian, i64;
+        read_u128_endian, u128;
+        read_i128_endian, i128;
+        read_f32_endian, f32;
+        read_f64_endian, f64;
+    }
+
+    read_slice! {
+        read_u16_slice, u16;
+        read_i16_slice, i16;
+        read_u32_slice, u32;
+        read_i32_slice, i32;
+        read_u64_slice, u64;
+        read_i64_slice, i64;
+        read_u128_slice, u128;
+        read_i128_slice, i128;
+        read_f32_slice, f32;
+        read_f64_slice, f64;
+    }
+
+    read_or! {
+        read_u8_or, read_u8, u8;
+        read_i8_or, read_i8, i8;
+        read_u16_or, read_u16, u16;
+        read_i16_or, read_i16, i16;
+        read_u32_or, read_u32, u32;
+        read_i32_or, read_i32, i32;
+        read_u64_or, read_u64, u64;
+        read_i64_or, read_i64, i64;
+        read_u128_or, read_u128, u128;
+        read_i128_or, read_i128, i128;
+        read_f32_or, read_f32, f32;
+        read_f64_or, read_f64, f64;
+        read_bool_or, read_bool, bool;
+    }
+
+    /// Read a `u64` encoded using unsigned LEB128.
+    pub fn read_varint_u64(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for index in 0..VARINT_MAX_LEN {
+            let byte = self.read_u8()?;
+            if decode_varint(&mut value, index, byte)? {
+                return Ok(value);
+            }
+        }
+        Err(varint_unterminated())
+    }
+
+    /// Read an `i64` encoded using signed LEB128 where the sign is
+    /// extended from the high bit of the final group.
+    pub fn read_varint_i64_signext(&mut self) -> Result<i64> {
+        let mut value = 0;
+        for index in 0..VARINT_MAX_LEN {
+            let byte = self.read_u8()?;
+            if decode_varint_signext(&mut value, index, byte)? {
+                return Ok(value);
+            }
+        }
+        Err(varint_unterminated())
+    }
+
+    /// Read an `i64` written using `write_varint_i64`.
+    pub fn read_varint_i64(&mut self) -> Result<i64> {
+        let value = self.read_varint_u64()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Read a sequence of `u64` written using `write_delta_varint`.
+    pub fn read_delta_varint(&mut self) -> Result<Vec<u64>> {
+        let count = self.read_varint_u64()?;
+        guard_size!(count, self.options.max_buffer_size);
+        let mut values = Vec::new();
+        let mut previous = 0;
+        for _ in 0..count {
+            previous = delta_add(previous, self.read_varint_u64()?)?;
+            values.push(previous);
+        }
+        Ok(values)
+    }
+
+    /// Read a list of strings written using
+    /// `write_string_list_prefix_compressed`.
+    pub fn read_string_list_prefix_compressed(
+        &mut self,
+    ) -> Result<Vec<String>> {
+        let count = self.read_varint_u64()?;
+        guard_size!(count, self.options.max_buffer_size);
+        let mut values: Vec<String> = Vec::new();
+        for _ in 0..count {
+            let shared = self.read_varint_u64()? as usize;
+            let previous =
+                values.last().map(|v| v.as_bytes()).unwrap_or_default();
+            if shared > previous.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "shared prefix {} exceeds previous length {}",
+                        shared,
+                        previous.len()
+                    ),
+                ));
+            }
+            let mut bytes = previous[..shared].to_vec();
+            bytes.extend_from_slice(self.read_string()?.as_bytes());
+            values.push(String::from_utf8(bytes).map_err(|_| {
+                Error::new(ErrorKind::InvalidData, "invalid utf-8")
+            })?);
+        }
+        Ok(values)
+    }
+
+    /// Count the occurrences of a delimiter byte from the current
+    /// position to the end of the stream without consuming any bytes.
+    ///
+    /// Bytes are scanned in chunks of `io_chunk_size` and the stream
+    /// position is restored afterwards.
+    pub fn count_delimiters(&mut self, delim: u8) -> Result<usize> {
+        guard_chunk_size!(self.options.io_chunk_size);
+        let position = self.stream.stream_position()?;
+        let mut buffer = vec![0; self.options.io_chunk_size];
+        let mut count = 0;
+        let result = loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break Ok(count),
+                Ok(read) => {
+                    count += buffer[..read]
+                        .iter()
+                        .filter(|byte| **byte == delim)
+                        .count();
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.stream.seek(SeekFrom::Start(position))?;
+        result
+    }
+
     /// Read bytes from the stream into a buffer.
+    ///
+    /// Skips any padding written to reach the field alignment.
     pub fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let buffer = self.read_buffer(length)?;
+        self.skip_padding()?;
+        Ok(buffer)
+    }
+
+    /// Read bytes into a buffer ignoring the field alignment.
+    fn read_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
         guard_size!(length, self.options.max_buffer_size);
+        self.check_budget(length as u64)?;
         let mut buffer: Vec<u8> = vec![0; length];
-        self.stream.read_exact(&mut buffer)?;
+        self.fill_buffer(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Read a field of exactly `width` bytes written using
+    /// `write_record_field` and trim the trailing NUL bytes.
+    pub fn read_record_field(&mut self, width: usize) -> Result<Vec<u8>> {
+        let mut field = self.read_buffer(width)?;
+        let len = field
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        field.truncate(len);
+        Ok(field)
+    }
+
+    /// Skip padding up to the field alignment.
+    fn skip_padding(&mut self) -> Result<()> {
+        let Some(alignment) = self.options.field_alignment else {
+            return Ok(());
+        };
+        let position = self.stream_position()?;
+        let padding = field_padding(position, alignment);
+        if padding > 0 {
+            let mut buffer = vec![0; padding as usize];
+            self.fill_buffer(&mut buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Read run-length encoded bytes written using `write_rle`
+    /// and expand them to exactly `decoded_len` bytes.
+    pub fn read_rle(&mut self, decoded_len: usize) -> Result<Vec<u8>> {
+        guard_size!(decoded_len, self.options.max_buffer_size);
+        let mut buffer = Vec::with_capacity(decoded_len);
+        while buffer.len() < decoded_len {
+            let count = self.read_u8()? as usize;
+            let value = self.read_u8()?;
+            if count == 0 || buffer.len() + count > decoded_len {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "run-length encoding does not decode to {} bytes",
+                        decoded_len
+                    ),
+                ));
+            }
+            buffer.resize(buffer.len() + count, value);
+        }
+        Ok(buffer)
+    }
+
+    /// Copy the remaining bytes in the stream to a writer and
+    /// return the number of bytes copied.
+    ///
+    /// Bytes are read in chunks of `io_chunk_size`.
+    pub fn copy_to<W: Write>(&mut self, writer: &mut W) -> Result<u64> {
+        guard_chunk_size!(self.options.io_chunk_size);
+        let mut buffer = vec![0; self.options.io_chunk_size];
+        let mut copied = 0;
+        loop {
+            let read = self.read_chunk(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            copied += read as u64;
+        }
+        Ok(copied)
+    }
+
+    /// Read the remaining bytes in the stream into a buffer.
+    ///
+    /// Bytes are read in chunks of `io_chunk_size`.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        guard_chunk_size!(self.options.io_chunk_size);
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0; self.options.io_chunk_size];
+        loop {
+            let read = self.read_chunk(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            guard_size!(buffer.len() + read, self.options.max_buffer_size);
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        Ok(buffer)
+    }
+
+    /// Get a reader that errors once more than `limit` bytes
+    /// have been read.
+    ///
+    /// The limit is a byte budget so this reader is left wherever
+    /// reading stopped.
+    pub fn take(&mut self, limit: u64) -> ChildReader<'_, &mut R> {
+        let budget =
+            self.budget.map_or(limit, |remaining| remaining.min(limit));
+        ChildReader::new(
+            &mut self.stream,
+            self.options.clone(),
+            self.budget.as_mut(),
+            &mut self.consumed,
+            Some(budget),
+        )
+    }
+
+    /// Read a `Vec` encoded as a length followed by the elements
+    /// and keep the elements decoded before an error.
+    ///
+    /// Returns the decoded elements and the error that stopped
+    /// decoding, if any. After an error the stream is positioned at
+    /// the start of the element that failed to decode.
+    pub fn read_vec_lenient<T>(&mut self) -> (Vec<T>, Option<Error>)
+    where
+        T: Decodable + Default,
+    {
+        let mut items = Vec::new();
+        let len = if cfg!(feature = "64bit") {
+            self.read_u64()
+        } else {
+            self.read_u32().map(|len| len as u64)
+        };
+        let len = match len {
+            Ok(len) => len,
+            Err(e) => return (items, Some(e)),
+        };
+        for _ in 0..len {
+            let position = match self.stream_position() {
+                Ok(position) => position,
+                Err(e) => return (items, Some(e)),
+            };
+            let mut item = T::default();
+            if let Err(e) = item.decode(&mut *self) {
+                let _ = self.seek(SeekFrom::Start(position));
+                return (items, Some(e));
+            }
+            items.push(item);
+        }
+        (items, None)
+    }
+
+    /// Read exactly `count` elements that were written without
+    /// a length prefix using `write_elements`.
+    pub fn read_elements<T>(&mut self, count: usize) -> Result<Vec<T>>
+    where
+        T: Decodable + Default,
+    {
+        guard_size!(count, self.options.max_buffer_size);
+        let mut items = Vec::new();
+        for _ in 0..count {
+            let mut item = T::default();
+            item.decode(&mut *self)?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read a `Wide` value and convert it to `Narrow`.
+    ///
+    /// Errors with `InvalidData` if the value does not fit in
+    /// `Narrow` rather than truncating it.
+    pub fn read_narrowed<Wide, Narrow>(&mut self) -> Result<Narrow>
+    where
+        Wide: Decodable + Default + Copy + Display,
+        Narrow: TryFrom<Wide>,
+    {
+        let mut value = Wide::default();
+        value.decode(&mut *self)?;
+        Narrow::try_from(value).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "value {} does not fit in {}",
+                    value,
+                    std::any::type_name::<Narrow>()
+                ),
+            )
+        })
+    }
+
+    /// Read a `u64` value that must fit in a `u16`.
+    pub fn read_u16_from_u64(&mut self) -> Result<u16> {
+        self.read_narrowed::<u64, u16>()
+    }
+
+    /// Read a list written using `write_list_suffix_count` that
+    /// ends at the end of the stream.
+    ///
+    /// The count is read from the end of the stream and the stream
+    /// is positioned after the count once the elements are decoded.
+    pub fn read_list_suffix_count<T>(&mut self) -> Result<Vec<T>>
+    where
+        T: Decodable + Default,
+    {
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        let start = self.stream_position()?;
+        let end = self.len()?;
+        if end.saturating_sub(start) < prefix {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "stream is too short for a list count",
+            ));
+        }
+        self.seek(SeekFrom::Start(end - prefix))?;
+        let count = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        guard_size!(count, self.options.max_buffer_size);
+        self.seek(SeekFrom::Start(start))?;
+        let items = self.read_elements(count as usize)?;
+        if self.stream_position()? != end - prefix {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "list elements do not end at the count",
+            ));
+        }
+        self.seek(SeekFrom::Start(end))?;
+        Ok(items)
+    }
+
+    /// Read a matrix written using `write_matrix` and return the
+    /// number of rows, the number of columns and the row-major
+    /// elements.
+    pub fn read_matrix<T>(&mut self) -> Result<(usize, usize, Vec<T>)>
+    where
+        T: Decodable + Default,
+    {
+        let rows = self.read_u64()?;
+        let cols = self.read_u64()?;
+        let overflow = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("matrix dimensions {}x{} overflow", rows, cols),
+            )
+        };
+        let rows = usize::try_from(rows).map_err(|_| overflow())?;
+        let cols = usize::try_from(cols).map_err(|_| overflow())?;
+        let count = rows.checked_mul(cols).ok_or_else(overflow)?;
+        let items = self.read_elements(count)?;
+        Ok((rows, cols, items))
+    }
+
+    /// Read a sparse array written using `write_sparse` and fill
+    /// the missing entries with the default value.
+    ///
+    /// Errors if an index is out of range or the indices are not
+    /// strictly increasing. Without `max_buffer_size` the length may
+    /// not exceed the number of bytes remaining in the stream.
+    pub fn read_sparse<T>(&mut self) -> Result<Vec<T>>
+    where
+        T: Decodable + Default,
+    {
+        let len = self.read_varint_u64()?;
+        guard_size!(len, self.options.max_buffer_size);
+        if self.options.max_buffer_size.is_none() {
+            let remaining = self.len()? - self.stream_position()?;
+            if len > remaining {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "sparse length {} exceeds remaining length {}",
+                        len, remaining
+                    ),
+                ));
+            }
+        }
+        let count = self.read_varint_u64()?;
+        if count > len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("sparse count {} exceeds length {}", count, len),
+            ));
+        }
+        let mut items = Vec::new();
+        let mut previous = None;
+        for _ in 0..count {
+            let index = self.read_varint_u64()?;
+            if index >= len || previous.is_some_and(|p| index <= p) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid sparse index {}", index),
+                ));
+            }
+            previous = Some(index);
+            items.resize_with(index as usize, T::default);
+            let mut item = T::default();
+            item.decode(&mut *self)?;
+            items.push(item);
+        }
+        items.resize_with(len as usize, T::default);
+        Ok(items)
+    }
+
+    /// Decode records until the predicate rejects a record or
+    /// the end of the stream is reached.
+    ///
+    /// The stream is positioned before the rejected record. A record
+    /// that decodes without reading any bytes is an error.
+    pub fn read_while<T, F>(&mut self, mut pred: F) -> Result<Vec<T>>
+    where
+        T: Decodable + Default,
+        F: FnMut(&T) -> bool,
+    {
+        let length = self.len()?;
+        let mut items = Vec::new();
+        loop {
+            let position = self.stream_position()?;
+            if position >= length {
+                break;
+            }
+            let mut item = T::default();
+            item.decode(&mut *self)?;
+            if self.stream_position()? == position {
+                return Err(no_progress(position));
+            }
+            if !pred(&item) {
+                self.seek(SeekFrom::Start(position))?;
+                break;
+            }
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read bytes one at a time until the predicate rejects the
+    /// bytes read so far or the end of the stream is reached.
+    ///
+    /// The byte that caused the predicate to reject the bytes is
+    /// not included, the stream is positioned before it and it is
+    /// not charged to the byte budget.
+    pub fn read_while_bytes<F>(&mut self, mut pred: F) -> Result<Vec<u8>>
+    where
+        F: FnMut(&[u8]) -> bool,
+    {
+        let length = self.len()?;
+        let mut buffer = Vec::new();
+        while self.stream_position()? < length {
+            let (budget, consumed) = (self.budget, self.consumed);
+            buffer.push(self.read_u8()?);
+            if !pred(&buffer) {
+                buffer.pop();
+                self.budget = budget;
+                self.consumed = consumed;
+                self.seek(SeekFrom::Current(-1))?;
+                break;
+            }
+            guard_size!(buffer.len(), self.options.max_buffer_size);
+        }
         Ok(buffer)
     }
+
+    /// Read optional bytes written using `write_optional_bytes`.
+    pub fn read_optional_bytes(&mut self) -> Result<Option<Vec<u8>>> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        if length == optional_sentinel() {
+            return Ok(None);
+        }
+        Ok(Some(self.read_buffer(length as usize)?))
+    }
+
+    /// Read a length written using `write_checked_len` and verify
+    /// the check byte before returning the length.
+    ///
+    /// Errors with `InvalidData` if the check byte does not match.
+    pub fn read_checked_len(&mut self) -> Result<u64> {
+        let length = self.read_u64()?;
+        let check = self.read_u8()?;
+        if check != length_check(length) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("corrupt length prefix {}", length),
+            ));
+        }
+        Ok(length)
+    }
+
+    /// Read a frame written using `write_framed`, verify the checksum
+    /// and return a reader for the body of the frame.
+    pub fn read_framed(&mut self) -> Result<BinaryReader<Cursor<Vec<u8>>>> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        let expected = self.read_u32()?;
+        let body = self.read_buffer(length as usize)?;
+        let actual = crc32::checksum(&body);
+        if actual != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "frame checksum {:#010x} does not match {:#010x}",
+                    actual, expected
+                ),
+            ));
+        }
+        Ok(BinaryReader::new(Cursor::new(body), self.options.clone()))
+    }
+
+    /// Read the length prefix of a block written using `write_sized`
+    /// and return a reader bounded to the block.
+    pub fn read_sized(
+        &mut self,
+    ) -> Result<ChildReader<'_, SubStream<&mut R>>> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        self.sized_child(length)
+    }
+
+    /// Get a reader bounded to the next `length` bytes whose byte
+    /// budget is charged to this reader.
+    fn sized_child(
+        &mut self,
+        length: u64,
+    ) -> Result<ChildReader<'_, SubStream<&mut R>>> {
+        let stream = SubStream::new(&mut self.stream, length)?;
+        let budget = self.budget.map(|remaining| remaining.min(length));
+        Ok(ChildReader::new(
+            stream,
+            self.options.clone(),
+            self.budget.as_mut(),
+            &mut self.consumed,
+            budget,
+        ))
+    }
+
+    /// Call a closure with a reader bounded to `length` bytes
+    /// starting at `offset` and restore the stream position
+    /// afterwards.
+    ///
+    /// Positions in the sub reader are relative to `offset`.
+    pub fn subreader<T, F>(
+        &mut self,
+        offset: u64,
+        length: u64,
+        f: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&mut BinaryReader<SubStream<&mut R>>) -> Result<T>,
+    {
+        let position = self.stream.stream_position()?;
+        self.stream.seek(SeekFrom::Start(offset))?;
+        let result =
+            self.sized_child(length).and_then(|mut child| f(&mut child));
+        self.stream.seek(SeekFrom::Start(position))?;
+        result
+    }
+
+    /// Read records prefixed by a `u16` type and `u32` size until
+    /// the end of the stream and call the closure with a reader
+    /// bounded to each record.
+    ///
+    /// Bytes the closure does not consume are skipped so unknown
+    /// record types can be ignored by returning immediately.
+    pub fn for_each_record<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(u16, &mut BinaryReader<SubStream<&mut R>>) -> Result<()>,
+    {
+        let end = self.len()?;
+        while self.stream_position()? < end {
+            let kind = self.read_u16()?;
+            let size = self.read_u32()? as u64;
+            let start = self.stream_position()?;
+            if size > end - start {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!(
+                        "record size {} exceeds remaining length {}",
+                        size,
+                        end - start
+                    ),
+                ));
+            }
+            f(kind, &mut *self.sized_child(size)?)?;
+            self.stream.seek(SeekFrom::Start(start + size))?;
+        }
+        Ok(())
+    }
+
+    /// Read an envelope written using `write_envelope` and return
+    /// the version and the decoded body.
+    ///
+    /// Errors if the magic bytes do not match or the version is not
+    /// in the accepted range; bytes in the body after the decoded
+    /// value are skipped.
+    pub fn read_envelope<T>(
+        &mut self,
+        magic: &[u8],
+        accepted_versions: RangeInclusive<u32>,
+    ) -> Result<(u32, T)>
+    where
+        T: Decodable + Default,
+    {
+        if self.read_buffer(magic.len())? != magic {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "envelope magic does not match",
+            ));
+        }
+        let version = self.read_u32()?;
+        if !accepted_versions.contains(&version) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported envelope version {}", version),
+            ));
+        }
+        let mut body = self.read_sized_checked()?;
+        let mut value = T::default();
+        value.decode(&mut body)?;
+        body.seek(SeekFrom::End(0))?;
+        Ok((version, value))
+    }
+
+    /// Read a value written using `write_framed_value` and decode
+    /// it with a reader bounded to the length prefix.
+    ///
+    /// Errors with `InvalidData` if the value does not decode
+    /// exactly the number of bytes in the length prefix.
+    pub fn read_framed_value<T>(&mut self) -> Result<T>
+    where
+        T: Decodable + Default,
+    {
+        let mut body = self.read_sized_checked()?;
+        let mut value = T::default();
+        value.decode(&mut body)?;
+        let (read, length) = (body.stream_position()?, body.len()?);
+        if read != length {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "value decoded {} bytes of length prefix {}",
+                    read, length
+                ),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Read the length prefix of a block written using `write_sized`
+    /// and return a reader bounded to the block.
+    ///
+    /// Errors with `UnexpectedEof` if the length exceeds the number
+    /// of bytes remaining in the stream.
+    pub fn read_sized_checked(
+        &mut self,
+    ) -> Result<ChildReader<'_, SubStream<&mut R>>> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        let remaining = self.len()? - self.stream_position()?;
+        if length > remaining {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "block length {} exceeds remaining length {}",
+                    length, remaining
+                ),
+            ));
+        }
+        self.sized_child(length)
+    }
+}
+
+/// Reader borrowed from another reader by `take` or `read_sized`.
+///
+/// Dereferences to a `BinaryReader` whose byte budget is capped by
+/// the remaining budget of the parent and deducts the bytes it read
+/// from the parent budget when dropped, including bytes read before
+/// a call to `reset` or `restore`.
+pub struct ChildReader<'a, S>
+where
+    S: Read + Seek,
+{
+    reader: BinaryReader<S>,
+    parent_budget: Option<&'a mut u64>,
+    parent_consumed: &'a mut u64,
+}
+
+impl<'a, S: Read + Seek> ChildReader<'a, S> {
+    fn new(
+        stream: S,
+        options: Options,
+        parent_budget: Option<&'a mut u64>,
+        parent_consumed: &'a mut u64,
+        budget: Option<u64>,
+    ) -> Self {
+        let mut reader = BinaryReader::new(stream, options);
+        reader.budget = budget;
+        reader.initial_budget = budget;
+        Self {
+            reader,
+            parent_budget,
+            parent_consumed,
+        }
+    }
+}
+
+impl<S: Read + Seek> Deref for ChildReader<'_, S> {
+    type Target = BinaryReader<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl<S: Read + Seek> DerefMut for ChildReader<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+impl<S: Read + Seek> Drop for ChildReader<'_, S> {
+    fn drop(&mut self) {
+        let consumed = self.reader.consumed;
+        if let Some(parent) = self.parent_budget.as_mut() {
+            **parent = parent.saturating_sub(consumed);
+        }
+        *self.parent_consumed = self.parent_consumed.saturating_add(consumed);
+    }
 }
 
 /// Write to a stream.
@@ -305,22 +2140,52 @@ impl<W: Write + Seek> BinaryWriter<W> {
         Self { stream, options }
     }
 
+    /// Seek to the start of the stream and replace the options
+    /// so the writer can be reused.
+    ///
+    /// The stream is not truncated so subsequent writes overwrite
+    /// any existing data; use `reset_truncate` to discard it.
+    pub fn reset(&mut self, options: Options) -> Result<()> {
+        self.stream.seek(SeekFrom::Start(0))?;
+        self.options = options;
+        Ok(())
+    }
+
     /// Seek to a position.
     pub fn seek(&mut self, to: SeekFrom) -> Result<u64> {
-        Ok(self.stream.seek(to)?)
+        self.stream.seek(to)
     }
 
     /// Get the current seek position.
     pub fn stream_position(&mut self) -> Result<u64> {
-        Ok(self.stream.stream_position()?)
+        self.stream.stream_position()
     }
 
     /// Get the length of this stream by seeking to the end
     /// and then restoring the previous cursor position.
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&mut self) -> Result<u64> {
         stream_length(&mut self.stream)
     }
 
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &W {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading, writing or seeking the stream directly bypasses
+    /// the options for this writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.stream
+    }
+
+    /// Consume this writer and return the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
     /// Write a length-prefixed `String` to the stream.
     pub fn write_string<S: AsRef<str>>(&mut self, value: S) -> Result<usize> {
         let bytes = value.as_ref().as_bytes();
@@ -330,12 +2195,93 @@ impl<W: Write + Seek> BinaryWriter<W> {
         } else {
             self.write_u32(bytes.len() as u32)?;
         }
-        Ok(self.stream.write(bytes)?)
+        let written = self.stream.write(bytes)?;
+        Ok(written + self.write_padding()?)
+    }
+
+    /// Write a string padded with NUL bytes to exactly `len` bytes.
+    ///
+    /// Errors if the string is longer than `len` bytes.
+    pub fn write_string_fixed<S: AsRef<str>>(
+        &mut self,
+        value: S,
+        len: usize,
+    ) -> Result<usize> {
+        self.write_record_field(value.as_ref().as_bytes(), len)
+    }
+
+    /// Write a length-prefixed `String` to the stream and
+    /// error if it contains more than `max_chars` characters.
+    pub fn write_string_max_chars<S: AsRef<str>>(
+        &mut self,
+        value: S,
+        max_chars: usize,
+    ) -> Result<usize> {
+        guard_chars!(value.as_ref(), max_chars);
+        self.write_string(value)
+    }
+
+    /// Write a path as a length-prefixed UTF-8 string.
+    ///
+    /// Errors if the path is not valid UTF-8. When the
+    /// `portable_paths` option is set the platform separator is
+    /// written as a forward slash.
+    pub fn write_path<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let value = encode_path(path.as_ref(), self.options.portable_paths)?;
+        self.write_string(value)
+    }
+
+    /// Write a UTF-16 string followed by a zero code unit.
+    ///
+    /// Errors if the string contains a NUL character.
+    pub fn write_cstring_utf16<S: AsRef<str>>(
+        &mut self,
+        value: S,
+    ) -> Result<usize> {
+        let value = value.as_ref();
+        if value.contains('\0') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "string must not contain a nul character",
+            ));
+        }
+        guard_size!(value.len() * 2, self.options.max_buffer_size);
+        let mut written = 0;
+        for unit in value.encode_utf16() {
+            written += self.write_u16(unit)?;
+        }
+        Ok(written + self.write_u16(0)?)
     }
 
     /// Write a character to the stream.
     pub fn write_char<V: Borrow<char>>(&mut self, v: V) -> Result<usize> {
-        self.write_u32(*v.borrow() as u32)
+        let value = *v.borrow() as u32;
+        let data = match self.options.char_endian() {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+            Endian::Native => value.to_ne_bytes(),
+        };
+        self.stream.write(&data)
+    }
+
+    /// Write a presence flag.
+    ///
+    /// Errors if the reason for an absent value is zero as zero
+    /// indicates a value is present.
+    pub fn write_presence(&mut self, presence: Presence) -> Result<usize> {
+        match presence {
+            Presence::Present => self.write_u8(0),
+            Presence::Absent(0) => {
+                Err(Error::other("absent reason must not be zero"))
+            }
+            Presence::Absent(reason) => self.write_u8(reason),
+        }
+    }
+
+    /// Write a schema hash so readers can detect a change to
+    /// the schema using `check_schema_hash`.
+    pub fn write_schema_hash(&mut self, hash: u32) -> Result<usize> {
+        self.write_u32(hash)
     }
 
     /// Write a `bool` to the stream.
@@ -345,15 +2291,58 @@ impl<W: Write + Seek> BinaryWriter<W> {
     }
 
     /// Write a `f32` to the stream.
+    ///
+    /// The bits of the value are copied without any floating point
+    /// operations so subnormals and NaN payloads are preserved
+    /// exactly regardless of the FPU mode.
     pub fn write_f32<V: Borrow<f32>>(&mut self, value: V) -> Result<usize> {
         encode_endian!(self.options.endian, value.borrow(), self.stream);
     }
 
-    /// Write a `f64` to the stream.
-    pub fn write_f64<V: Borrow<f64>>(&mut self, value: V) -> Result<usize> {
+    /// Write a slice of `f32` values and return the number of
+    /// bytes written.
+    ///
+    /// When the endian is the native byte order the slice is written
+    /// as a single buffer without converting each value.
+    #[cfg(feature = "bytemuck")]
+    pub fn write_f32_slice_native(
+        &mut self,
+        values: &[f32],
+    ) -> Result<usize> {
+        if self.options.endian.resolve() == Endian::native() {
+            let bytes: &[u8] = bytemuck::cast_slice(values);
+            self.stream.write_all(bytes)?;
+            return Ok(bytes.len());
+        }
+        let mut written = 0;
+        for value in values {
+            written += self.write_f32(value)?;
+        }
+        Ok(written)
+    }
+
+    /// Write a `f64` to the stream.
+    ///
+    /// The bits of the value are copied without any floating point
+    /// operations so subnormals and NaN payloads are preserved
+    /// exactly regardless of the FPU mode.
+    pub fn write_f64<V: Borrow<f64>>(&mut self, value: V) -> Result<usize> {
         encode_endian!(self.options.endian, value.borrow(), self.stream);
     }
 
+    write_endian! {
+        write_u16_endian, u16;
+        write_i16_endian, i16;
+        write_u32_endian, u32;
+        write_i32_endian, i32;
+        write_u64_endian, u64;
+        write_i64_endian, i64;
+        write_u128_endian, u128;
+        write_i128_endian, i128;
+        write_f32_endian, f32;
+        write_f64_endian, f64;
+    }
+
     /// Write an `isize` to the stream.
     pub fn write_isize<V: Borrow<isize>>(
         &mut self,
@@ -420,10 +2409,470 @@ impl<W: Write + Seek> BinaryWriter<W> {
         encode_endian!(self.options.endian, value.borrow(), self.stream);
     }
 
+    /// Write a `u64` using unsigned LEB128 variable-length encoding.
+    ///
+    /// The encoding is byte-order independent so the endian
+    /// in the options is ignored.
+    pub fn write_varint_u64<V: Borrow<u64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let (buffer, length) = encode_varint(*value.borrow());
+        self.stream.write_all(&buffer[..length])?;
+        Ok(length)
+    }
+
+    /// Write an `i64` using signed LEB128 and return the number
+    /// of bytes written.
+    ///
+    /// Unlike zig-zag encoding which interleaves negative and positive
+    /// values the groups are written in two's complement and the
+    /// sign is extended from the high bit of the final group, so
+    /// `-1` is `0x7f` and `64` needs two bytes.
+    pub fn write_varint_i64_signext<V: Borrow<i64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let (buffer, length) = encode_varint_signext(*value.borrow());
+        self.stream.write_all(&buffer[..length])?;
+        Ok(length)
+    }
+
+    /// Write an `i64` using zig-zag encoding followed by unsigned
+    /// LEB128 and return the number of bytes written.
+    ///
+    /// Zig-zag maps values of small magnitude to small unsigned
+    /// values so `-1` is `0x01` and `1` is `0x02`.
+    pub fn write_varint_i64<V: Borrow<i64>>(
+        &mut self,
+        value: V,
+    ) -> Result<usize> {
+        let value = *value.borrow();
+        self.write_varint_u64(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    /// Write a list of strings as a varint count followed by the
+    /// varint length of the prefix shared with the previous string
+    /// and the remaining suffix as a length-prefixed string.
+    ///
+    /// Returns the number of bytes written; sorted lists share the
+    /// longest prefixes so compress best.
+    pub fn write_string_list_prefix_compressed(
+        &mut self,
+        sorted: &[&str],
+    ) -> Result<usize> {
+        let start = self.stream_position()?;
+        self.write_varint_u64(sorted.len() as u64)?;
+        let mut previous = "";
+        for value in sorted {
+            let mut shared = previous
+                .bytes()
+                .zip(value.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !value.is_char_boundary(shared) {
+                shared -= 1;
+            }
+            self.write_varint_u64(shared as u64)?;
+            self.write_string(&value[shared..])?;
+            previous = value;
+        }
+        Ok((self.stream_position()? - start) as usize)
+    }
+
+    /// Write a sequence of non-decreasing `u64` values as a varint
+    /// count followed by the varint difference from the previous
+    /// value; the first value is written as-is.
+    pub fn write_delta_varint(&mut self, values: &[u64]) -> Result<usize> {
+        guard_size!(values.len(), self.options.max_buffer_size);
+        let mut written = self.write_varint_u64(values.len() as u64)?;
+        let mut previous = 0;
+        for value in values {
+            written += self.write_varint_u64(delta_sub(previous, *value)?)?;
+            previous = *value;
+        }
+        Ok(written)
+    }
+
     /// Write a byte buffer to the stream.
     pub fn write_bytes<B: AsRef<[u8]>>(&mut self, data: B) -> Result<usize> {
         guard_size!(data.as_ref().len(), self.options.max_buffer_size);
-        Ok(self.stream.write(data.as_ref())?)
+        let written = self.stream.write(data.as_ref())?;
+        Ok(written + self.write_padding()?)
+    }
+
+    /// Write zero padding up to the field alignment.
+    fn write_padding(&mut self) -> Result<usize> {
+        match self.options.field_alignment {
+            Some(alignment) => {
+                let position = self.stream_position()?;
+                let padding = field_padding(position, alignment) as usize;
+                self.write_padding_with(0, padding)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Write `n` copies of a fill byte.
+    pub fn write_padding_with(
+        &mut self,
+        byte: u8,
+        n: usize,
+    ) -> Result<usize> {
+        if n > 0 {
+            self.stream.write_all(&vec![byte; n])?;
+        }
+        Ok(n)
+    }
+
+    /// Write bytes padded with NUL bytes to exactly `width` bytes,
+    /// for example a fixed width field in a tar header.
+    ///
+    /// Errors if the bytes are longer than `width`.
+    pub fn write_record_field(
+        &mut self,
+        bytes: &[u8],
+        width: usize,
+    ) -> Result<usize> {
+        if bytes.len() > width {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "field length {} exceeds width {}",
+                    bytes.len(),
+                    width
+                ),
+            ));
+        }
+        self.stream.write_all(bytes)?;
+        self.write_padding_with(0, width - bytes.len())?;
+        Ok(width)
+    }
+
+    /// Write zero padding until the position is a multiple of
+    /// `alignment` and return the number of padding bytes written.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub fn align_to(&mut self, alignment: u64) -> Result<u64> {
+        self.align_to_with(0, alignment)
+    }
+
+    /// Write copies of a fill byte until the position is a multiple
+    /// of `alignment` and return the number of padding bytes written.
+    ///
+    /// Errors if `alignment` is not a power of two.
+    pub fn align_to_with(&mut self, byte: u8, alignment: u64) -> Result<u64> {
+        check_alignment(alignment)?;
+        let position = self.stream_position()?;
+        let padding = field_padding(position, alignment);
+        self.write_padding_with(byte, padding as usize)?;
+        Ok(padding)
+    }
+
+    /// Write bytes as run-length encoded `[count:u8][value:u8]` pairs.
+    ///
+    /// The decoded length is not written so it must be stored
+    /// separately to read the bytes using `read_rle`.
+    pub fn write_rle(&mut self, data: &[u8]) -> Result<usize> {
+        let mut written = 0;
+        for run in data.chunk_by(|a, b| a == b) {
+            for chunk in run.chunks(u8::MAX as usize) {
+                self.stream.write_all(&[chunk.len() as u8, chunk[0]])?;
+                written += 2;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Encode a value and return the range of stream positions
+    /// it occupies.
+    pub fn write_tracked(
+        &mut self,
+        encodable: &impl Encodable,
+    ) -> Result<Range<u64>> {
+        let start = self.stream_position()?;
+        encodable.encode(&mut *self)?;
+        Ok(start..self.stream_position()?)
+    }
+
+    /// Write elements without a length prefix and return the
+    /// number of bytes written.
+    ///
+    /// Unlike the implementation for `Vec<T>` the count must be
+    /// stored separately to read the elements using `read_elements`.
+    pub fn write_elements<T>(&mut self, items: &[T]) -> Result<usize>
+    where
+        T: Encodable,
+    {
+        let start = self.stream_position()?;
+        for item in items {
+            item.encode(&mut *self)?;
+        }
+        Ok((self.stream_position()? - start) as usize)
+    }
+
+    /// Write elements followed by the count so the count can be
+    /// computed in a single forward pass; returns the number of
+    /// bytes written.
+    ///
+    /// The count is a `u32` (or `u64` with the `64bit` feature)
+    /// and the list must be at the end of the stream to be read
+    /// using `read_list_suffix_count`.
+    pub fn write_list_suffix_count<T>(&mut self, items: &[T]) -> Result<usize>
+    where
+        T: Encodable,
+    {
+        let mut written = self.write_elements(items)?;
+        written += if cfg!(feature = "64bit") {
+            self.write_u64(items.len() as u64)?
+        } else {
+            let len: u32 = items.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    items.len()
+                ))
+            })?;
+            self.write_u32(len)?
+        };
+        Ok(written)
+    }
+
+    /// Write a matrix as the `u64` number of rows and columns
+    /// followed by the row-major elements and return the number
+    /// of bytes written.
+    pub fn write_matrix<T>(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        data: &[T],
+    ) -> Result<usize>
+    where
+        T: Encodable,
+    {
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "matrix dimensions {}x{} do not match {} elements",
+                    rows,
+                    cols,
+                    data.len()
+                ),
+            ));
+        }
+        let start = self.stream_position()?;
+        self.write_u64(rows as u64)?;
+        self.write_u64(cols as u64)?;
+        self.write_elements(data)?;
+        Ok((self.stream_position()? - start) as usize)
+    }
+
+    /// Write the length of a sparse array, the number of entries
+    /// that are not the default value and the varint encoded index
+    /// and value for each of those entries.
+    ///
+    /// Returns the number of bytes written.
+    pub fn write_sparse<T>(&mut self, items: &[T]) -> Result<usize>
+    where
+        T: Encodable + Default + PartialEq,
+    {
+        let start = self.stream_position()?;
+        let default = T::default();
+        let count = items.iter().filter(|item| **item != default).count();
+        self.write_varint_u64(items.len() as u64)?;
+        self.write_varint_u64(count as u64)?;
+        for (index, item) in items.iter().enumerate() {
+            if *item != default {
+                self.write_varint_u64(index as u64)?;
+                item.encode(&mut *self)?;
+            }
+        }
+        Ok((self.stream_position()? - start) as usize)
+    }
+
+    /// Write a map as an entry count followed by the key and
+    /// value for each entry sorted by key and return the number
+    /// of bytes written.
+    ///
+    /// Sorting the entries means equal maps always encode to the
+    /// same bytes regardless of insertion order and the bytes
+    /// decode as a `HashMap<K, V>`.
+    pub fn write_map_canonical<K, V>(
+        &mut self,
+        map: &HashMap<K, V>,
+    ) -> Result<usize>
+    where
+        K: Encodable + Ord,
+        V: Encodable,
+    {
+        let start = self.stream_position()?;
+        if cfg!(feature = "64bit") {
+            self.write_u64(map.len() as u64)?;
+        } else {
+            let len: u32 = map.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    map.len()
+                ))
+            })?;
+            self.write_u32(len)?;
+        }
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in entries {
+            key.encode(&mut *self)?;
+            value.encode(&mut *self)?;
+        }
+        Ok((self.stream_position()? - start) as usize)
+    }
+
+    /// Write optional length-prefixed bytes to the stream.
+    ///
+    /// `None` is encoded as a length prefix using the maximum value
+    /// of the prefix type so a buffer of that length cannot be written.
+    pub fn write_optional_bytes(
+        &mut self,
+        value: Option<&[u8]>,
+    ) -> Result<usize> {
+        let length = match value {
+            Some(bytes) => {
+                guard_size!(bytes.len(), self.options.max_buffer_size);
+                guard_sentinel!(bytes.len());
+                bytes.len() as u64
+            }
+            None => optional_sentinel(),
+        };
+        let mut written = if cfg!(feature = "64bit") {
+            self.write_u64(length)?
+        } else {
+            self.write_u32(length as u32)?
+        };
+        if let Some(bytes) = value {
+            written += self.stream.write(bytes)?;
+        }
+        Ok(written)
+    }
+
+    /// Write a `u64` length followed by a check byte so a corrupt
+    /// length is detected by `read_checked_len` before it is used.
+    pub fn write_checked_len(&mut self, len: u64) -> Result<usize> {
+        let written = self.write_u64(len)?;
+        Ok(written + self.write_u8(length_check(len))?)
+    }
+
+    /// Write a frame of the data written by the closure prefixed
+    /// with the length and CRC-32 checksum of the data.
+    ///
+    /// The body is buffered in memory so the length and checksum
+    /// can be written before the body without seeking.
+    pub fn write_framed<F>(&mut self, f: F) -> Result<usize>
+    where
+        F: FnOnce(&mut BinaryWriter<Cursor<Vec<u8>>>) -> Result<()>,
+    {
+        let mut body =
+            BinaryWriter::new(Cursor::new(Vec::new()), self.options.clone());
+        f(&mut body)?;
+        let body = body.stream.into_inner();
+        let mut written = if cfg!(feature = "64bit") {
+            self.write_u64(body.len() as u64)?
+        } else {
+            let length: u32 = body.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    body.len()
+                ))
+            })?;
+            self.write_u32(length)?
+        };
+        written += self.write_u32(crc32::checksum(&body))?;
+        self.stream.write_all(&body)?;
+        Ok(written + body.len())
+    }
+
+    /// Write an envelope of magic bytes, a `u32` version and the
+    /// encoded body prefixed with the byte length of the body and
+    /// return the length of the body.
+    pub fn write_envelope(
+        &mut self,
+        magic: &[u8],
+        version: u32,
+        body: &impl Encodable,
+    ) -> Result<u64> {
+        self.stream.write_all(magic)?;
+        self.write_u32(version)?;
+        self.write_sized(|writer| body.encode(writer))
+    }
+
+    /// Write a value prefixed with its encoded byte length so
+    /// readers can skip it and return the length of the value.
+    ///
+    /// The length prefix is the same as for `write_sized`.
+    pub fn write_framed_value(
+        &mut self,
+        value: &impl Encodable,
+    ) -> Result<u64> {
+        self.write_sized(|writer| value.encode(writer))
+    }
+
+    /// Write a block prefixed with the byte length of the data
+    /// written by the closure and return the length of the block.
+    ///
+    /// The length prefix is a `u32` or a `u64` when the `64bit`
+    /// feature is enabled.
+    pub fn write_sized<F>(&mut self, f: F) -> Result<u64>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let size_pos = self.stream_position()?;
+        let prefix = if cfg!(feature = "64bit") {
+            self.write_u64(0)?
+        } else {
+            self.write_u32(0)?
+        };
+        f(self)?;
+        let end_pos = self.stream_position()?;
+        let length = end_pos - (size_pos + prefix as u64);
+        self.seek(SeekFrom::Start(size_pos))?;
+        if cfg!(feature = "64bit") {
+            self.write_u64(length)?;
+        } else {
+            let length: u32 = length.try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    length
+                ))
+            })?;
+            self.write_u32(length)?;
+        }
+        self.seek(SeekFrom::Start(end_pos))?;
+        Ok(length)
+    }
+
+    /// Append the total length of the stream as a `u64` so readers
+    /// can locate data relative to the end using
+    /// `read_trailer_length`, then flush the stream.
+    ///
+    /// The length includes the eight bytes of the trailer; when
+    /// `total_len_field` is false no trailer is written. Returns
+    /// the length of the stream.
+    pub fn finalize_with_trailer(
+        &mut self,
+        total_len_field: bool,
+    ) -> Result<u64> {
+        let mut length = self.stream.seek(SeekFrom::End(0))?;
+        if total_len_field {
+            length += 8;
+            self.write_u64(length)?;
+        }
+        self.flush()?;
+        Ok(length)
+    }
+
+    /// Append footer magic bytes to the end of the stream.
+    pub fn write_footer(&mut self, magic: &[u8]) -> Result<usize> {
+        self.stream.seek(SeekFrom::End(0))?;
+        self.stream.write_all(magic)?;
+        Ok(magic.len())
     }
 
     /// Flush the write buffer.
@@ -439,6 +2888,22 @@ pub trait Encodable {
         &self,
         writer: &mut BinaryWriter<W>,
     ) -> Result<()>;
+
+    /// Encode self into the binary writer overriding the endian
+    /// of the writer for the duration of the call.
+    fn encode_with<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+        endian: Option<Endian>,
+    ) -> Result<()> {
+        let Some(endian) = endian else {
+            return self.encode(writer);
+        };
+        let previous = std::mem::replace(&mut writer.options.endian, endian);
+        let result = self.encode(&mut *writer);
+        writer.options.endian = previous;
+        result
+    }
 }
 
 /// Trait for decoding from binary.
@@ -448,9 +2913,34 @@ pub trait Decodable {
         &mut self,
         reader: &mut BinaryReader<R>,
     ) -> Result<()>;
+
+    /// Decode from the binary reader into self overriding the endian
+    /// of the reader for the duration of the call.
+    fn decode_with<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+        endian: Option<Endian>,
+    ) -> Result<()> {
+        let Some(endian) = endian else {
+            return self.decode(reader);
+        };
+        let previous = std::mem::replace(&mut reader.options.endian, endian);
+        let result = self.decode(&mut *reader);
+        reader.options.endian = previous;
+        result
+    }
+}
+
+/// Check byte for a length using the low byte of the CRC-32
+/// checksum of the little endian bytes of the length.
+fn length_check(length: u64) -> u8 {
+    crc32::checksum(&length.to_le_bytes()) as u8
 }
 
 /// Encode to a binary buffer.
+///
+/// Wraps the buffer in a `Cursor` so encoding to bytes does not
+/// need a stream.
 pub fn encode(
     encodable: &impl Encodable,
     options: Options,
@@ -462,7 +2952,24 @@ pub fn encode(
     Ok(buffer)
 }
 
+/// Encode into a fixed size buffer and return the number of
+/// bytes used.
+///
+/// Errors with `WriteZero` if the buffer is too small.
+pub fn encode_into_slice(
+    encodable: &impl Encodable,
+    buffer: &mut [u8],
+    options: Options,
+) -> Result<usize> {
+    let mut stream = fixed::FixedBuffer::new(buffer);
+    encode_stream(encodable, &mut stream, options)?;
+    Ok(stream.used())
+}
+
 /// Decode from a binary buffer.
+///
+/// Wraps the buffer in a `Cursor` so decoding from a byte slice
+/// does not need a stream.
 pub fn decode<T: Decodable + Default>(
     buffer: &[u8],
     options: Options,
@@ -471,6 +2978,49 @@ pub fn decode<T: Decodable + Default>(
     decode_stream::<T, _>(&mut stream, options)
 }
 
+/// Decode from a binary buffer and error if any bytes remain
+/// after decoding.
+pub fn decode_exact<T: Decodable + Default>(
+    buffer: &[u8],
+    options: Options,
+) -> Result<T> {
+    let mut stream = Cursor::new(buffer);
+    let decoded = decode_stream::<T, _>(&mut stream, options)?;
+    let remaining = (buffer.len() as u64).saturating_sub(stream.position());
+    if remaining > 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} trailing bytes after decoding", remaining),
+        ));
+    }
+    Ok(decoded)
+}
+
+/// Decode a value from a buffer and encode it again.
+///
+/// Useful to check that the encode and decode implementations
+/// for a type are inverses of each other.
+pub fn decode_and_reencode<T: Decodable + Encodable + Default>(
+    buffer: &[u8],
+    options: Options,
+) -> Result<Vec<u8>> {
+    let decoded: T = decode(buffer, options.clone())?;
+    encode(&decoded, options)
+}
+
+/// Determine if a buffer encodes to the same bytes after it has
+/// been decoded.
+///
+/// Some types are legitimately unstable: a `HashMap` encodes in
+/// iteration order, non-zero values other than one decode as `true`
+/// for a `bool` and trailing bytes after the value are dropped.
+pub fn roundtrip_stable<T: Decodable + Encodable + Default>(
+    buffer: &[u8],
+    options: Options,
+) -> Result<bool> {
+    Ok(decode_and_reencode::<T>(buffer, options)? == buffer)
+}
+
 /// Encode to a stream.
 pub fn encode_stream<S>(
     encodable: &impl Encodable,
@@ -486,15 +3036,42 @@ where
     Ok(())
 }
 
+/// Encode to the end of a stream and return the range of stream
+/// positions the value occupies.
+///
+/// Useful to build a single buffer of many values with an index
+/// of the range for each value.
+pub fn append_encode<S>(
+    encodable: &impl Encodable,
+    stream: &mut S,
+    options: Options,
+) -> Result<Range<u64>>
+where
+    S: Write + Seek,
+{
+    let mut writer = BinaryWriter::new(stream, options);
+    writer.seek(SeekFrom::End(0))?;
+    let range = writer.write_tracked(encodable)?;
+    writer.flush()?;
+    Ok(range)
+}
+
 /// Decode from a stream.
 pub fn decode_stream<T: Decodable + Default, S: Read + Seek>(
     stream: &mut S,
     options: Options,
 ) -> Result<T> {
     let mut reader = BinaryReader::new(stream, options);
-    let mut decoded: T = T::default();
-    decoded.decode(&mut reader)?;
-    Ok(decoded)
+    #[cfg(feature = "diagnostics")]
+    {
+        reader.decode_diagnostic()
+    }
+    #[cfg(not(feature = "diagnostics"))]
+    {
+        let mut decoded: T = T::default();
+        decoded.decode(&mut reader)?;
+        Ok(decoded)
+    }
 }
 
 impl<T> Encodable for Option<T>
@@ -539,7 +3116,17 @@ where
         &self,
         writer: &mut BinaryWriter<W>,
     ) -> Result<()> {
-        writer.write_u32(self.len() as u32)?;
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64)?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len)?;
+        }
         for item in self {
             item.encode(&mut *writer)?;
         }
@@ -555,7 +3142,12 @@ where
         &mut self,
         reader: &mut BinaryReader<R>,
     ) -> Result<()> {
-        let len = reader.read_u32()?;
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64()?
+        } else {
+            reader.read_u32()? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
         for _ in 0..len {
             let mut item = T::default();
             item.decode(&mut *reader)?;
@@ -565,70 +3157,462 @@ where
     }
 }
 
-macro_rules! impl_encode_decode {
-    ($type:ty, $read:ident, $write:ident) => {
-        impl Encodable for $type {
-            fn encode<W: Write + Seek>(
-                &self,
-                writer: &mut BinaryWriter<W>,
-            ) -> Result<()> {
-                writer.$write(self)?;
-                Ok(())
-            }
+impl<T> Encodable for BinaryHeap<T>
+where
+    T: Encodable + Ord,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64)?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len)?;
         }
-
-        impl Decodable for $type {
-            fn decode<R: Read + Seek>(
-                &mut self,
-                reader: &mut BinaryReader<R>,
-            ) -> Result<()> {
-                *self = reader.$read()?;
-                Ok(())
-            }
+        for item in self {
+            item.encode(&mut *writer)?;
         }
-    };
+        Ok(())
+    }
 }
 
-impl_encode_decode!(u8, read_u8, write_u8);
-impl_encode_decode!(u16, read_u16, write_u16);
-impl_encode_decode!(u32, read_u32, write_u32);
-impl_encode_decode!(u64, read_u64, write_u64);
-impl_encode_decode!(u128, read_u128, write_u128);
-impl_encode_decode!(usize, read_usize, write_usize);
-
-impl_encode_decode!(i8, read_i8, write_i8);
-impl_encode_decode!(i16, read_i16, write_i16);
-impl_encode_decode!(i32, read_i32, write_i32);
-impl_encode_decode!(i64, read_i64, write_i64);
-impl_encode_decode!(i128, read_i128, write_i128);
-impl_encode_decode!(isize, read_isize, write_isize);
-
-impl_encode_decode!(f32, read_f32, write_f32);
-impl_encode_decode!(f64, read_f64, write_f64);
-
-impl_encode_decode!(bool, read_bool, write_bool);
-impl_encode_decode!(char, read_char, write_char);
-impl_encode_decode!(String, read_string, write_string);
-
-#[cfg(test)]
-mod tests {
-    use super::{BinaryReader, BinaryWriter, Endian, Options};
-    use anyhow::Result;
-    use std::io::{Cursor, SeekFrom};
-    use tempfile::tempfile;
-
-    #[test]
-    fn max_buffer_size() -> Result<()> {
-        let options = Options {
-            endian: Endian::Little,
-            max_buffer_size: Some(1024),
+impl<T> Decodable for BinaryHeap<T>
+where
+    T: Decodable + Default + Ord,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64()?
+        } else {
+            reader.read_u32()? as u64
         };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut item = T::default();
+            item.decode(&mut *reader)?;
+            self.push(item);
+        }
+        Ok(())
+    }
+}
 
-        let mut buffer = Vec::new();
-        let stream = Cursor::new(&mut buffer);
-        let mut writer = BinaryWriter::new(stream, options.clone());
-
-        let large_string = ".".repeat(2048);
+impl<K, V> Encodable for HashMap<K, V>
+where
+    K: Encodable,
+    V: Encodable,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64)?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len)?;
+        }
+        for (key, value) in self {
+            key.encode(&mut *writer)?;
+            value.encode(&mut *writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> Decodable for HashMap<K, V>
+where
+    K: Decodable + Default + Eq + Hash,
+    V: Decodable + Default,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64()?
+        } else {
+            reader.read_u32()? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut key = K::default();
+            key.decode(&mut *reader)?;
+            let mut value = V::default();
+            value.decode(&mut *reader)?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> Encodable for BTreeMap<K, V>
+where
+    K: Encodable,
+    V: Encodable,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        if cfg!(feature = "64bit") {
+            writer.write_u64(self.len() as u64)?;
+        } else {
+            let len: u32 = self.len().try_into().map_err(|_| {
+                Error::other(format!(
+                    "length {} exceeds the size prefix",
+                    self.len()
+                ))
+            })?;
+            writer.write_u32(len)?;
+        }
+        for (key, value) in self {
+            key.encode(&mut *writer)?;
+            value.encode(&mut *writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> Decodable for BTreeMap<K, V>
+where
+    K: Decodable + Default + Ord,
+    V: Decodable + Default,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let len = if cfg!(feature = "64bit") {
+            reader.read_u64()?
+        } else {
+            reader.read_u32()? as u64
+        };
+        guard_size!(len, reader.options.max_buffer_size);
+        for _ in 0..len {
+            let mut key = K::default();
+            key.decode(&mut *reader)?;
+            let mut value = V::default();
+            value.decode(&mut *reader)?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+impl<T> Encodable for Range<T>
+where
+    T: Encodable,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.start.encode(&mut *writer)?;
+        self.end.encode(&mut *writer)?;
+        Ok(())
+    }
+}
+
+impl<T> Decodable for Range<T>
+where
+    T: Decodable + Default,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        self.start.decode(&mut *reader)?;
+        self.end.decode(&mut *reader)?;
+        Ok(())
+    }
+}
+
+impl<T> Encodable for RangeInclusive<T>
+where
+    T: Encodable,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.start().encode(&mut *writer)?;
+        self.end().encode(&mut *writer)?;
+        Ok(())
+    }
+}
+
+impl<T> Decodable for RangeInclusive<T>
+where
+    T: Decodable + Default,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        let mut start = T::default();
+        start.decode(&mut *reader)?;
+        let mut end = T::default();
+        end.decode(&mut *reader)?;
+        *self = start..=end;
+        Ok(())
+    }
+}
+
+impl<T> Encodable for Wrapping<T>
+where
+    T: Encodable,
+{
+    fn encode<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<()> {
+        self.0.encode(writer)
+    }
+}
+
+impl<T> Decodable for Wrapping<T>
+where
+    T: Decodable,
+{
+    fn decode<R: Read + Seek>(
+        &mut self,
+        reader: &mut BinaryReader<R>,
+    ) -> Result<()> {
+        self.0.decode(reader)
+    }
+}
+
+macro_rules! impl_encode_decode {
+    ($type:ty, $read:ident, $write:ident) => {
+        impl Encodable for $type {
+            fn encode<W: Write + Seek>(
+                &self,
+                writer: &mut BinaryWriter<W>,
+            ) -> Result<()> {
+                writer.$write(self)?;
+                Ok(())
+            }
+        }
+
+        impl Decodable for $type {
+            fn decode<R: Read + Seek>(
+                &mut self,
+                reader: &mut BinaryReader<R>,
+            ) -> Result<()> {
+                *self = reader.$read()?;
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_encode_decode!(u8, read_u8, write_u8);
+impl_encode_decode!(u16, read_u16, write_u16);
+impl_encode_decode!(u32, read_u32, write_u32);
+impl_encode_decode!(u64, read_u64, write_u64);
+impl_encode_decode!(u128, read_u128, write_u128);
+impl_encode_decode!(usize, read_usize, write_usize);
+
+impl_encode_decode!(i8, read_i8, write_i8);
+impl_encode_decode!(i16, read_i16, write_i16);
+impl_encode_decode!(i32, read_i32, write_i32);
+impl_encode_decode!(i64, read_i64, write_i64);
+impl_encode_decode!(i128, read_i128, write_i128);
+impl_encode_decode!(isize, read_isize, write_isize);
+
+impl_encode_decode!(f32, read_f32, write_f32);
+impl_encode_decode!(f64, read_f64, write_f64);
+
+impl_encode_decode!(bool, read_bool, write_bool);
+impl_encode_decode!(char, read_char, write_char);
+impl_encode_decode!(String, read_string, write_string);
+impl_encode_decode!(PathBuf, read_path, write_path);
+
+/// Implement `Encodable` and `Decodable` for a tuple which encodes
+/// each element in order without a length prefix.
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name),+> Encodable for ($($name,)+)
+        where
+            $($name: Encodable,)+
+        {
+            fn encode<W: Write + Seek>(
+                &self,
+                writer: &mut BinaryWriter<W>,
+            ) -> Result<()> {
+                $(self.$index.encode(&mut *writer)?;)+
+                Ok(())
+            }
+        }
+
+        impl<$($name),+> Decodable for ($($name,)+)
+        where
+            $($name: Decodable + Default,)+
+        {
+            fn decode<R: Read + Seek>(
+                &mut self,
+                reader: &mut BinaryReader<R>,
+            ) -> Result<()> {
+                $(self.$index.decode(&mut *reader)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// Compute a 32-bit FNV-1a hash of a schema description.
+///
+/// Use the `schema_hash!` macro to compute the hash from a list
+/// of field types at compile time.
+pub const fn schema_hash(schema: &str) -> u32 {
+    let bytes = schema.as_bytes();
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+/// Compute a schema hash from the names of the field types in
+/// order for use with `write_schema_hash` and `check_schema_hash`.
+///
+/// Only the type names as written are hashed so changing the type
+/// or order of the fields changes the hash.
+#[macro_export]
+macro_rules! schema_hash {
+    ($($type:ty),* $(,)?) => {
+        $crate::schema_hash(concat!($(stringify!($type), ";"),*))
+    };
+}
+
+/// Implement `Encodable` and `Decodable` for a single field tuple
+/// struct by delegating to the inner field so the newtype is
+/// encoded exactly as the inner value.
+#[macro_export]
+macro_rules! impl_transparent {
+    ($type:ty) => {
+        impl $crate::Encodable for $type {
+            fn encode<W: std::io::Write + std::io::Seek>(
+                &self,
+                writer: &mut $crate::BinaryWriter<W>,
+            ) -> std::io::Result<()> {
+                $crate::Encodable::encode(&self.0, writer)
+            }
+        }
+
+        impl $crate::Decodable for $type {
+            fn decode<R: std::io::Read + std::io::Seek>(
+                &mut self,
+                reader: &mut $crate::BinaryReader<R>,
+            ) -> std::io::Result<()> {
+                $crate::Decodable::decode(&mut self.0, reader)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::needless_borrows_for_generic_args,
+    clippy::unnecessary_to_owned
+)]
+mod tests {
+    use super::{
+        append_encode, decode, decode_exact, encode, encode_into_slice,
+        roundtrip_stable, BinaryReader, BinaryWriter, Decodable, Encodable,
+        Endian, ErrorPolicy, Options, Presence,
+    };
+    use anyhow::Result;
+    use std::{
+        collections::{BTreeMap, BinaryHeap, HashMap},
+        io::{self, Cursor, Read, Seek, SeekFrom, Write},
+        num::Wrapping,
+        path::PathBuf,
+    };
+    use tempfile::tempfile;
+
+    #[derive(Debug, Default, Eq, PartialEq)]
+    struct Header {
+        kind: u16,
+        length: u32,
+    }
+
+    impl Encodable for Header {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            self.kind.encode(&mut *writer)?;
+            self.length.encode_with(&mut *writer, Some(Endian::Big))?;
+            Ok(())
+        }
+    }
+
+    impl Decodable for Header {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            self.kind.decode(&mut *reader)?;
+            self.length.decode_with(&mut *reader, Some(Endian::Big))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encode_decode_endian_override() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+        let buffer = encode(&header, Default::default())?;
+        assert_eq!(vec![1, 0, 0, 0, 0, 2], buffer);
+        let decoded: Header = decode(&buffer, Default::default())?;
+        assert_eq!(header, decoded);
+        Ok(())
+    }
+
+    #[test]
+    fn max_buffer_size() -> Result<()> {
+        let options = Options {
+            endian: Endian::Little,
+            max_buffer_size: Some(1024),
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::new();
+        let stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(stream, options.clone());
+
+        let large_string = ".".repeat(2048);
         let result = writer.write_string(&large_string);
         assert!(result.is_err());
 
@@ -902,201 +3886,2070 @@ mod tests {
     }
 
     #[test]
-    fn read_write_test_i64() -> Result<()> {
-        let temp: i64 = i64::MAX;
+    fn read_write_test_i64() -> Result<()> {
+        let temp: i64 = i64::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_i64(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_i64()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_i128() -> Result<()> {
+        let temp: i128 = i128::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_i128(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_i128()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_i32() -> Result<()> {
+        let temp: i32 = i32::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_i32(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_i32()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_i16() -> Result<()> {
+        let temp: i16 = i16::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_i16(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_i16()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_i8() -> Result<()> {
+        let temp: i8 = i8::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_i8(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_i8()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_u64() -> Result<()> {
+        let temp: u64 = u64::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_u64(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_u64()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_u128() -> Result<()> {
+        let temp: u128 = u128::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_u128(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_u128()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_u32() -> Result<()> {
+        let temp: u32 = u32::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_u32(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_u32()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_u16() -> Result<()> {
+        let temp: u16 = u16::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_u16(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_u16()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_test_u8() -> Result<()> {
+        let temp: u8 = u8::MAX;
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_u8(temp)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_u8()?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_bytes() -> Result<()> {
+        let count = 20;
+
+        let temp = vec![16; count];
+
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+
+        writer.write_bytes(temp.clone())?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+
+        let read_temp = reader.read_bytes(count)?;
+        assert_eq!(temp, read_temp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_optional_bytes() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_optional_bytes(None)?;
+        writer.write_optional_bytes(Some(&[]))?;
+        writer.write_optional_bytes(Some(&[1, 2, 3, 4]))?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(None, reader.read_optional_bytes()?);
+        assert_eq!(Some(vec![]), reader.read_optional_bytes()?);
+        assert_eq!(Some(vec![1, 2, 3, 4]), reader.read_optional_bytes()?);
+
+        let options = Options {
+            endian: Endian::Little,
+            max_buffer_size: Some(2),
+            ..Default::default()
+        };
+        let mut writer = BinaryWriter::new(&mut stream, options.clone());
+        assert!(writer.write_optional_bytes(Some(&[1, 2, 3, 4])).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(None, reader.read_optional_bytes()?);
+        assert_eq!(Some(vec![]), reader.read_optional_bytes()?);
+        assert!(reader.read_optional_bytes().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_byte_budget() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        for i in 0..16u16 {
+            writer.write_u16(i)?;
+        }
+
+        stream.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            9,
+        );
+        for i in 0..4u16 {
+            assert_eq!(i, reader.read_u16()?);
+        }
+        assert_eq!(4, reader.read_u8()?);
+        let error = reader.read_u16().unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+        assert!(reader.read_bytes(1).is_err());
+
+        // Limit applies across every read from the taken reader
+        stream.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let mut limited = reader.take(6);
+        for i in 0..3u16 {
+            assert_eq!(i, limited.read_u16()?);
+        }
+        assert!(limited.read_u8().is_err());
+        drop(limited);
+        assert_eq!(3, reader.read_u16()?);
+
+        // Taken reader is capped by and charged to the parent budget
+        stream.seek(SeekFrom::Start(0))?;
+        let options = Options {
+            on_read_error: ErrorPolicy::DefaultValue,
+            ..Default::default()
+        };
+        let mut reader =
+            BinaryReader::with_byte_budget(&mut stream, options, 5);
+        let mut limited = reader.take(8);
+        assert_eq!(0, limited.read_u16()?);
+        assert!(limited.read_u32().is_err());
+        drop(limited);
+        assert_eq!(1, reader.read_u16()?);
+        assert!(reader.read_u32().is_err());
+        assert!(reader.last_error().is_none());
+
+        // Bytes read before a reset are still charged to the parent
+        stream.seek(SeekFrom::Start(0))?;
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            6,
+        );
+        let mut limited = reader.take(4);
+        limited.read_u32()?;
+        limited.reset(Default::default())?;
+        assert_eq!(0, limited.read_u16()?);
+        drop(limited);
+        assert!(reader.read_u8().is_err());
+
+        // Sized readers are charged to the parent budget
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_sized(|writer| {
+            writer.write_string("text")?;
+            Ok(())
+        })?;
+        writer.write_u32(7)?;
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        stream.seek(SeekFrom::Start(0))?;
+        let budget = prefix * 2 + 4 + 2;
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            budget,
+        );
+        assert_eq!("text", reader.read_sized()?.read_string()?);
+        assert!(reader.read_u32().is_err());
+
+        // Prefix exceeding the budget errors before allocating
+        stream.seek(SeekFrom::Start(prefix))?;
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            prefix + 3,
+        );
+        let error = reader.read_string().unwrap_err();
+        assert!(error.to_string().contains("byte budget"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reset_reader() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+        let buffer = encode(&header, Default::default())?;
+
+        let mut stream = Cursor::new(buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+
+        let mut decoded: Header = Default::default();
+        decoded.decode(&mut reader)?;
+        assert_eq!(header, decoded);
+
+        reader.reset(Endian::Big.into())?;
+        assert_eq!(0, reader.stream_position()?);
+        assert_eq!(256, reader.read_u16()?);
+
+        reader.reset(Default::default())?;
+        let mut decoded: Header = Default::default();
+        decoded.decode(&mut reader)?;
+        assert_eq!(header, decoded);
+
+        // Each pass gets the full byte budget
+        let len = reader.len()?;
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            len,
+        );
+        for _ in 0..2 {
+            reader.reset(Default::default())?;
+            let mut decoded: Header = Default::default();
+            decoded.decode(&mut reader)?;
+            assert_eq!(header, decoded);
+            assert!(reader.read_u8().is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_range() -> Result<()> {
+        let range = 0u32..100;
+        let buffer = encode(&range, Default::default())?;
+        let decoded: std::ops::Range<u32> =
+            decode(&buffer, Default::default())?;
+        assert_eq!(range, decoded);
+
+        let range = 1u8..=255;
+        let buffer = encode(&range, Default::default())?;
+        let mut stream = Cursor::new(buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let mut decoded = 0u8..=0;
+        decoded.decode(&mut reader)?;
+        assert_eq!(range, decoded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_sized() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        let length = writer.write_sized(|writer| {
+            header.encode(&mut *writer)?;
+            writer.write_string("foo")?;
+            Ok(())
+        })?;
+        writer.write_u8(42)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let prefix = if cfg!(feature = "64bit") {
+            reader.read_u64()?
+        } else {
+            reader.read_u32()? as u64
+        };
+        assert_eq!(length, prefix);
+        reader.seek(SeekFrom::Current(length as i64))?;
+        assert_eq!(42, reader.read_u8()?);
+
+        reader.seek(SeekFrom::Start(0))?;
+        let mut sized = reader.read_sized()?;
+        let mut decoded: Header = Default::default();
+        decoded.decode(&mut sized)?;
+        assert_eq!(header, decoded);
+        assert_eq!("foo", sized.read_string()?);
+        assert!(sized.read_u8().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn copy_to_chunks() -> Result<()> {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let options = Options {
+            io_chunk_size: 7,
+            ..Default::default()
+        };
+
+        let mut stream = Cursor::new(data.clone());
+        let mut reader = BinaryReader::new(&mut stream, options.clone());
+        let mut output = Vec::new();
+        assert_eq!(1000, reader.copy_to(&mut output)?);
+        assert_eq!(data, output);
+
+        reader.seek(SeekFrom::Start(500))?;
+        assert_eq!(&data[500..], reader.read_to_end()?.as_slice());
+
+        let options = Options {
+            io_chunk_size: 0,
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(&mut stream, options);
+        assert!(reader.copy_to(&mut output).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_at_offset() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(1)?;
+        writer.write_u16(2)?;
+        writer.write_u64(3)?;
+        writer.write_f32(4.0)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(3, reader.read_u64_at(6)?);
+        assert_eq!(1, reader.read_u32()?);
+        assert_eq!(4.0, reader.read_f32_at(14)?);
+        assert_eq!(2, reader.read_u16()?);
+        assert_eq!(1, reader.read_u32_at(0)?);
+        assert_eq!(3, reader.read_u64()?);
+        assert_eq!(14, reader.stream_position()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_delta_varint() -> Result<()> {
+        let values = vec![0, 1, 1, 300, 70_000, u64::MAX];
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_delta_varint(&values)?;
+        assert!(writer.write_delta_varint(&[2, 1]).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(values, reader.read_delta_varint()?);
+
+        // Second delta overflows when added to the first value
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_u64(2)?;
+        writer.write_varint_u64(u64::MAX)?;
+        writer.write_varint_u64(1)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_delta_varint().is_err());
+
+        // Count is not trusted for preallocation
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_u64(1 << 62)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_delta_varint().unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+
+        Ok(())
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Meters(f64);
+    crate::impl_transparent!(Meters);
+
+    #[test]
+    fn encode_decode_transparent() -> Result<()> {
+        let buffer = encode(&Meters(1.5), Default::default())?;
+        assert_eq!(encode(&1.5f64, Default::default())?, buffer);
+        assert_eq!(8, buffer.len());
+        let decoded: Meters = decode(&buffer, Default::default())?;
+        assert_eq!(Meters(1.5), decoded);
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_framed() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        let written = writer.write_framed(|writer| {
+            header.encode(&mut *writer)?;
+            writer.write_string("foo")?;
+            Ok(())
+        })?;
+        assert_eq!(written as u64, writer.len()?);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let mut framed = reader.read_framed()?;
+        let mut decoded: Header = Default::default();
+        decoded.decode(&mut framed)?;
+        assert_eq!(header, decoded);
+        assert_eq!("foo", framed.read_string()?);
+
+        // Flip a byte in the body
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0xff;
+        let mut stream = Cursor::new(&mut buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        assert!(reader.read_framed().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_delimiters() -> Result<()> {
+        let mut stream = Cursor::new(b"a,b,,c,".to_vec());
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(2))?;
+        assert_eq!(3, reader.count_delimiters(b',')?);
+        assert_eq!(2, reader.stream_position()?);
+        assert_eq!(b'b', reader.read_u8()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_vec_lenient() -> Result<()> {
+        let value: Vec<u32> = vec![1, 2, 3];
+        let mut buffer = encode(&value, Default::default())?;
+        buffer.truncate(buffer.len() - 2);
+
+        let mut stream = Cursor::new(buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let (items, error) = reader.read_vec_lenient::<u32>();
+        assert_eq!(vec![1, 2], items);
+        assert!(error.is_some());
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        assert_eq!(prefix + 8, reader.stream_position()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_field_alignment() -> Result<()> {
+        let options = Options {
+            field_alignment: Some(8),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, options.clone());
+        writer.write_string("foo")?;
+        assert_eq!(0, writer.stream_position()? % 8);
+        writer.write_bytes([1, 2, 3, 4, 5, 6, 7, 8, 9])?;
+        assert_eq!(0, writer.stream_position()? % 8);
+        writer.write_u8(8)?;
+
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!("foo", reader.read_string()?);
+        assert_eq!(0, reader.stream_position()? % 8);
+        assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 9], reader.read_bytes(9)?);
+        assert_eq!(0, reader.stream_position()? % 8);
+        assert_eq!(8, reader.read_u8()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_rle() -> Result<()> {
+        let mut data = vec![7u8; 300];
+        data.extend_from_slice(&[1, 2, 2, 3]);
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(10, writer.write_rle(&data)?);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(data, reader.read_rle(data.len())?);
+
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_rle(299).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_char_endian() -> Result<()> {
+        let bytes = [0, 0, 0, b'a'];
+        for endian in [Endian::Little, Endian::Big] {
+            let options = Options {
+                endian,
+                char_endian: Some(Endian::Big),
+                ..Default::default()
+            };
+            let mut stream = Cursor::new(bytes);
+            let mut reader = BinaryReader::new(&mut stream, options.clone());
+            assert_eq!('a', reader.read_char()?);
+
+            let mut buffer = Vec::new();
+            let mut stream = Cursor::new(&mut buffer);
+            let mut writer = BinaryWriter::new(&mut stream, options);
+            writer.write_char('a')?;
+            assert_eq!(bytes.to_vec(), buffer);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_while() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        for value in [1u32, 5, 9, 12, 3] {
+            writer.write_u32(value)?;
+        }
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let values: Vec<u32> = reader.read_while(|value| *value < 10)?;
+        assert_eq!(vec![1, 5, 9], values);
+        assert_eq!(12, reader.stream_position()?);
+        assert_eq!(12, reader.read_u32()?);
+
+        let values: Vec<u32> = reader.read_while(|_| true)?;
+        assert_eq!(vec![3], values);
+
+        // Records that read no bytes would never reach the end
+        #[derive(Debug, Default)]
+        struct Empty;
+
+        impl Decodable for Empty {
+            fn decode<R: Read + Seek>(
+                &mut self,
+                _reader: &mut BinaryReader<R>,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_while(|_: &Empty| true).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_into_fixed_buffer() -> Result<()> {
+        let header = Header {
+            kind: 1,
+            length: 16,
+        };
+
+        let mut buffer = [0u8; 4];
+        let result =
+            encode_into_slice(&header, &mut buffer, Default::default());
+        let Err(e) = result else {
+            panic!("expecting write zero error");
+        };
+        assert_eq!(io::ErrorKind::WriteZero, e.kind());
+
+        let mut buffer = [0u8; 256];
+        let used =
+            encode_into_slice(&header, &mut buffer, Default::default())?;
+        assert_eq!(6, used);
+        assert_eq!(encode(&header, Default::default())?, &buffer[..used]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_cstring_utf16() -> Result<()> {
+        let value = "key \u{1F600}";
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(14, writer.write_cstring_utf16(value)?);
+        writer.write_u8(8)?;
+        assert!(writer.write_cstring_utf16("a\0b").is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(value, reader.read_cstring_utf16()?);
+        assert_eq!(8, reader.read_u8()?);
+
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct Versioned {
+        value: u32,
+    }
+
+    impl Decodable for Versioned {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            let version = reader.context::<u8>().copied().unwrap_or(1);
+            self.value = if version > 1 {
+                reader.read_u32()?
+            } else {
+                reader.read_u16()? as u32
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn decode_context() -> Result<()> {
+        let value: Vec<u32> = vec![1, 2];
+        let buffer = encode(&value, Default::default())?;
+
+        let mut stream = Cursor::new(&buffer);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.set_context(2u8);
+        let mut values: Vec<Versioned> = Vec::new();
+        values.decode(&mut reader)?;
+        assert_eq!(
+            vec![1, 2],
+            values.iter().map(|v| v.value).collect::<Vec<_>>()
+        );
+
+        if let Some(version) = reader.context_mut::<u8>() {
+            *version = 3;
+        }
+        assert!(reader.take_context::<u16>().is_none());
+        assert_eq!(Some(3), reader.take_context::<u8>());
+        assert!(reader.context::<u8>().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_maps() -> Result<()> {
+        let mut map = HashMap::new();
+        map.insert("name".to_owned(), 1u32);
+        map.insert("size".to_owned(), 2u32);
+        let buffer = encode(&map, Default::default())?;
+        assert_eq!(map, decode(&buffer, Default::default())?);
+
+        let tree: BTreeMap<u16, String> =
+            [(2, "b".to_owned()), (1, "a".to_owned())].into();
+        let buffer = encode(&tree, Default::default())?;
+        assert_eq!(tree, decode(&buffer, Default::default())?);
+        let other: BTreeMap<u16, String> =
+            [(1, "a".to_owned()), (2, "b".to_owned())].into();
+        assert_eq!(buffer, encode(&other, Default::default())?);
+
+        // Entry count is prefixed like the length of a Vec
+        let empty: Vec<u8> = Vec::new();
+        assert_eq!(
+            encode(&empty, Default::default())?,
+            encode(&HashMap::<u8, u8>::new(), Default::default())?
+        );
+
+        // Hostile entry count is rejected before decoding entries
+        let options = Options {
+            max_buffer_size: Some(16),
+            ..Default::default()
+        };
+        let buffer = encode(&u32::MAX, Default::default())?;
+        assert!(decode::<HashMap<u8, u8>>(&buffer, options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn append_encode_ranges() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let first = append_encode(&1u32, &mut stream, Default::default())?;
+        stream.set_position(0);
+        let second =
+            append_encode(&Some(2u16), &mut stream, Default::default())?;
+        let third = append_encode(&3u64, &mut stream, Default::default())?;
+        assert_eq!(vec![0..4, 4..7, 7..15], vec![first, second, third]);
+
+        let buffer = stream.into_inner();
+        assert_eq!(15, buffer.len());
+        assert_eq!(2u16, decode::<u16>(&buffer[5..7], Default::default())?);
+        assert_eq!(3u64, decode::<u64>(&buffer[7..], Default::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_tuples() -> Result<()> {
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        let value = (7u32, "tuple".to_owned(), true);
+        let buffer = encode(&value, Default::default())?;
+        assert_eq!(4 + prefix + 5 + 1, buffer.len());
+        assert_eq!(value, decode(&buffer, Default::default())?);
+
+        let single = (1u8,);
+        assert_eq!(vec![1], encode(&single, Default::default())?);
+
+        let wide = (
+            1u8,
+            2u16,
+            3u32,
+            4u64,
+            5i8,
+            6i16,
+            7i32,
+            8i64,
+            9u8,
+            10u8,
+            Some(11u8),
+            'c',
+        );
+        let buffer = encode(&wide, Default::default())?;
+        assert_eq!(wide, decode(&buffer, Default::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_while_bytes() -> Result<()> {
+        let mut reader = BinaryReader::new(
+            Cursor::new(b"GET /\r\nHost"),
+            Default::default(),
+        );
+        let line =
+            reader.read_while_bytes(|bytes| !bytes.ends_with(b"\r\n"))?;
+        assert_eq!(b"GET /\r", line.as_slice());
+        assert_eq!(b'\n', reader.read_u8()?);
+        assert_eq!(b"Host", reader.read_while_bytes(|_| true)?.as_slice());
+
+        let options = Options {
+            max_buffer_size: Some(4),
+            ..Default::default()
+        };
+        let mut reader =
+            BinaryReader::new(Cursor::new(b"GET /\r\n"), options);
+        assert!(reader
+            .read_while_bytes(|bytes| !bytes.ends_with(b"\r\n"))
+            .is_err());
+
+        // Rejected byte is not charged to the byte budget
+        let mut reader = BinaryReader::with_byte_budget(
+            Cursor::new(b"GET /\r\n"),
+            Default::default(),
+            7,
+        );
+        let line =
+            reader.read_while_bytes(|bytes| !bytes.ends_with(b"\r\n"))?;
+        assert_eq!(b"GET /\r", line.as_slice());
+        assert_eq!(b'\n', reader.read_u8()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_and_peek() -> Result<()> {
+        let mut reader = BinaryReader::new(
+            Cursor::new([1u8, 2, 3, 4]),
+            Default::default(),
+        );
+        reader.skip(2)?;
+        assert_eq!(3, reader.peek_u8()?);
+        assert_eq!(3, reader.peek_u8()?);
+        assert_eq!(3, reader.read_u8()?);
+        reader.skip(1)?;
+        assert!(reader.peek_u8().is_err());
+        assert_eq!(4, reader.stream_position()?);
+        assert!(reader.skip(u64::MAX).is_err());
+
+        // Peeking neither spends the budget nor defaults at the end
+        let options = Options {
+            on_read_error: ErrorPolicy::DefaultValue,
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::with_byte_budget(
+            Cursor::new([1u8, 2]),
+            options.clone(),
+            2,
+        );
+        for _ in 0..4 {
+            assert_eq!(1, reader.peek_u8()?);
+        }
+        assert_eq!(1, reader.read_u8()?);
+        assert_eq!(2, reader.read_u8()?);
+        let mut reader = BinaryReader::new(Cursor::new([1u8]), options);
+        reader.skip(1)?;
+        assert!(reader.peek_u8().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_checked_len() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(9, writer.write_checked_len(1024)?);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(1024, reader.read_checked_len()?);
+
+        for bit in 0..64 {
+            let mut buffer = stream.get_ref().clone();
+            buffer[bit / 8] ^= 1 << (bit % 8);
+            let mut reader =
+                BinaryReader::new(Cursor::new(buffer), Default::default());
+            let error = reader.read_checked_len().unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn options_builder() -> Result<()> {
+        let options = Options::builder()
+            .endian(Endian::Big)
+            .max_buffer_size(4)
+            .char_endian(Endian::Little)
+            .build();
+        assert_eq!(Endian::Big, options.endian);
+        assert_eq!(Some(4), options.max_buffer_size);
+        assert_eq!(Endian::Little, options.char_endian());
+        assert_eq!(super::IO_CHUNK_SIZE, options.io_chunk_size);
+        assert!(options.portable_paths);
+
+        let buffer = encode(&0x0102u16, options.clone())?;
+        assert_eq!(vec![1, 2], buffer);
+        assert!(encode(&"too long".to_owned(), options).is_err());
+
+        let options: Options = Endian::Big.into();
+        assert_eq!(Endian::Big, options.endian);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_framed_value() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(6, writer.write_framed_value(&(1u32, 2u16))?);
+        assert_eq!(1, writer.write_framed_value(&3u8)?);
+        writer.write_u8(4)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!((1, 2), reader.read_framed_value::<(u32, u16)>()?);
+        assert_eq!(3, reader.read_framed_value::<u8>()?);
+        assert_eq!(4, reader.read_u8()?);
+
+        // Value decodes fewer bytes than the length prefix
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_framed_value::<u32>().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        // Corrupt length prefix shorter than the value
+        let mut buffer = stream.into_inner();
+        buffer[0] = 5;
+        let mut reader =
+            BinaryReader::new(Cursor::new(&buffer), Default::default());
+        assert!(reader.read_framed_value::<(u32, u16)>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_map_canonical() -> Result<()> {
+        let mut first = HashMap::new();
+        let mut second = HashMap::new();
+        for key in 0..32u16 {
+            first.insert(key, key as u32 * 2);
+            second.insert(31 - key, (31 - key) as u32 * 2);
+        }
+
+        let mut encoded = Vec::new();
+        for map in [&first, &second] {
+            let mut buffer = Vec::new();
+            let mut stream = Cursor::new(&mut buffer);
+            let mut writer =
+                BinaryWriter::new(&mut stream, Default::default());
+            let written = writer.write_map_canonical(map)?;
+            assert_eq!(written, buffer.len());
+            encoded.push(buffer);
+        }
+        assert_eq!(encoded[0], encoded[1]);
+
+        let map: HashMap<u16, u32> = decode(&encoded[0], Default::default())?;
+        assert_eq!(first, map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_sized_checked() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_sized(|writer| {
+            writer.write_u32(32)?;
+            Ok(())
+        })?;
+        writer.write_sized(|writer| {
+            writer.write_u64(64)?;
+            Ok(())
+        })?;
+
+        let mut stream = Cursor::new(&buffer[..buffer.len() - 1]);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        let mut block = reader.read_sized_checked()?;
+        assert_eq!(32, block.read_u32()?);
+        drop(block);
+
+        let Err(e) = reader.read_sized_checked() else {
+            panic!("expecting unexpected eof error");
+        };
+        assert_eq!(io::ErrorKind::UnexpectedEof, e.kind());
+
+        Ok(())
+    }
+
+    #[derive(Debug, Default, Eq, PartialEq)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    impl Encodable for Point {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            writer.write_schema_hash(crate::schema_hash!(u32, u32))?;
+            writer.write_u32(self.x)?;
+            writer.write_u32(self.y)?;
+            Ok(())
+        }
+    }
+
+    impl Decodable for Point {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            reader.check_schema_hash(crate::schema_hash!(u32, u32))?;
+            self.x = reader.read_u32()?;
+            self.y = reader.read_u32()?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct PointV2 {
+        x: u32,
+        y: u64,
+    }
+
+    impl Decodable for PointV2 {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            reader.check_schema_hash(crate::schema_hash!(u32, u64))?;
+            self.x = reader.read_u32()?;
+            self.y = reader.read_u64()?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encode_decode_schema_hash() -> Result<()> {
+        let point = Point { x: 1, y: 2 };
+        let buffer = encode(&point, Default::default())?;
+        assert_eq!(point, decode::<Point>(&buffer, Default::default())?);
+
+        let Err(e) = decode::<PointV2>(&buffer, Default::default()) else {
+            panic!("expecting schema mismatch error");
+        };
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_varint_i64() -> Result<()> {
+        let values = [
+            (0, vec![0x00]),
+            (-1, vec![0x01]),
+            (1, vec![0x02]),
+            (-64, vec![0x7f]),
+            (64, vec![0x80, 0x01]),
+        ];
+        for (value, expected) in values {
+            let mut buffer = Vec::new();
+            let mut stream = Cursor::new(&mut buffer);
+            let mut writer =
+                BinaryWriter::new(&mut stream, Endian::Big.into());
+            writer.write_varint_i64(value)?;
+            assert_eq!(expected, buffer);
+
+            let mut stream = Cursor::new(&buffer);
+            let mut reader =
+                BinaryReader::new(&mut stream, Default::default());
+            assert_eq!(value, reader.read_varint_i64()?);
+        }
+
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_i64(i64::MIN)?;
+        writer.write_varint_i64(i64::MAX)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(i64::MIN, reader.read_varint_i64()?);
+        assert_eq!(i64::MAX, reader.read_varint_i64()?);
+
+        let mut reader =
+            BinaryReader::new(Cursor::new([0xff; 11]), Default::default());
+        let e = reader.read_varint_i64().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_varint_i64_signext() -> Result<()> {
+        let values = [
+            (-1, vec![0x7f]),
+            (-128, vec![0x80, 0x7f]),
+            (63, vec![0x3f]),
+            (64, vec![0xc0, 0x00]),
+        ];
+        for (value, expected) in values {
+            let mut buffer = Vec::new();
+            let mut stream = Cursor::new(&mut buffer);
+            let mut writer =
+                BinaryWriter::new(&mut stream, Default::default());
+            writer.write_varint_i64_signext(value)?;
+            assert_eq!(expected, buffer);
+
+            let mut stream = Cursor::new(&buffer);
+            let mut reader =
+                BinaryReader::new(&mut stream, Default::default());
+            assert_eq!(value, reader.read_varint_i64_signext()?);
+        }
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(10, writer.write_varint_i64_signext(i64::MIN)?);
+        writer.write_varint_i64_signext(i64::MAX)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(i64::MIN, reader.read_varint_i64_signext()?);
+        assert_eq!(i64::MAX, reader.read_varint_i64_signext()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_elements() -> Result<()> {
+        let values: Vec<u16> = vec![1, 2, 3, 4, 5];
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(values.len() as u8)?;
+        assert_eq!(10, writer.write_elements(&values)?);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let count = reader.read_u8()? as usize;
+        assert_eq!(values, reader.read_elements::<u16>(count)?);
+        assert_eq!(11, reader.stream_position()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_path() -> Result<()> {
+        let unix = PathBuf::from("/usr/local/bin");
+        let buffer = encode(&unix, Default::default())?;
+        assert_eq!(unix, decode::<PathBuf>(&buffer, Default::default())?);
+
+        let windows = PathBuf::from(r"C:\Users\foo\file.txt");
+        let buffer = encode(&windows, Default::default())?;
+        #[cfg(windows)]
+        assert_eq!(
+            PathBuf::from("C:/Users/foo/file.txt"),
+            decode::<PathBuf>(&buffer, Default::default())?
+        );
+        #[cfg(unix)]
+        assert_eq!(windows, decode::<PathBuf>(&buffer, Default::default())?);
+
+        let options = Options {
+            portable_paths: false,
+            ..Default::default()
+        };
+        let buffer = encode(&windows, options.clone())?;
+        assert_eq!(windows, decode::<PathBuf>(&buffer, options)?);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn encode_non_utf8_path() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = PathBuf::from(OsStr::from_bytes(&[0x66, 0xff]));
+        assert!(encode(&path, Default::default()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn encode_path_backslash_file_name() -> Result<()> {
+        let path = PathBuf::from(r"/tmp/back\slash.txt");
+        let buffer = encode(&path, Default::default())?;
+        assert_eq!(path, decode::<PathBuf>(&buffer, Default::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_exact_trailing_bytes() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+        let mut buffer = encode(&header, Default::default())?;
+        assert_eq!(
+            header,
+            decode_exact::<Header>(&buffer, Default::default())?
+        );
+
+        buffer.push(0);
+        let Err(e) = decode_exact::<Header>(&buffer, Default::default())
+        else {
+            panic!("expecting trailing bytes error");
+        };
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_envelope() -> Result<()> {
+        let header = Header { kind: 1, length: 2 };
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_envelope(b"HEAD", 2, &header)?;
+        writer.write_u8(8)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let (version, decoded) = reader.read_envelope(b"HEAD", 1..=2)?;
+        assert_eq!(2, version);
+        assert_eq!(header, decoded);
+        assert_eq!(8, reader.read_u8()?);
+
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_envelope::<Header>(b"HEAD", 3..=4).is_err());
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_envelope::<Header>(b"BODY", 1..=2).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn write_f32_slice_native() -> Result<()> {
+        let values: Vec<f32> =
+            (0..64).map(|value| value as f32 / 4.0).collect();
+        for endian in [Endian::Little, Endian::Big] {
+            let mut fast = Cursor::new(Vec::new());
+            let mut writer = BinaryWriter::new(&mut fast, endian.into());
+            assert_eq!(256, writer.write_f32_slice_native(&values)?);
+
+            let mut slow = Cursor::new(Vec::new());
+            let mut writer = BinaryWriter::new(&mut slow, endian.into());
+            for value in &values {
+                writer.write_f32(value)?;
+            }
+            assert_eq!(slow.into_inner(), fast.into_inner());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_align_to() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(1)?;
+        assert_eq!(12, writer.align_to(16)?);
+        writer.write_u8(2)?;
+        assert_eq!(15, writer.align_to(16)?);
+        writer.write_u8(3)?;
+        assert!(writer.align_to(12).is_err());
+        assert!(writer.align_to(0).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(1, reader.read_u32()?);
+        assert_eq!(12, reader.align_to(16)?);
+        assert_eq!(0, reader.align_to(16)?);
+        assert_eq!(2, reader.read_u8()?);
+        assert_eq!(15, reader.align_to(16)?);
+        assert_eq!(3, reader.read_u8()?);
+        assert!(reader.align_to(24).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_align_to_with() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(1)?;
+        writer.write_u16(2)?;
+        assert_eq!(5, writer.align_to_with(0xcc, 8)?);
+        assert_eq!(0, writer.align_to_with(0xcc, 8)?);
+        writer.write_u8(3)?;
+        assert_eq!(3, writer.align_to(4)?);
+        assert_eq!(2, writer.write_padding_with(0x90, 2)?);
+
+        assert_eq!(
+            vec![
+                1, 2, 0, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 3, 0, 0, 0, 0x90, 0x90
+            ],
+            buffer
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_string_list_prefix_compressed() -> Result<()> {
+        let values =
+            ["apple", "applet", "apply", "banana", "band", "bé", "bê"];
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string_list_prefix_compressed(&values)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(
+            values.to_vec(),
+            reader.read_string_list_prefix_compressed()?
+        );
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_u64(1u64)?;
+        writer.write_varint_u64(1u64)?;
+        writer.write_string("a")?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_string_list_prefix_compressed().is_err());
+
+        // Count is not trusted for preallocation
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_u64(1 << 62)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_string_list_prefix_compressed().unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn subreader_record() -> Result<()> {
+        let headers = vec![
+            Header {
+                kind: 1,
+                length: 10,
+            },
+            Header {
+                kind: 2,
+                length: 20,
+            },
+            Header {
+                kind: 3,
+                length: 30,
+            },
+        ];
+        let mut file = tempfile()?;
+        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        for header in &headers {
+            header.encode(&mut writer)?;
+        }
+
+        let mut reader = BinaryReader::new(&mut file, Default::default());
+        reader.seek(SeekFrom::Start(2))?;
+        let header = reader.subreader(6, 6, |sub| {
+            assert_eq!(6, sub.len()?);
+            let mut header = Header::default();
+            header.decode(&mut *sub)?;
+            assert!(sub.read_u8().is_err());
+            Ok(header)
+        })?;
+        assert_eq!(headers[1], header);
+        assert_eq!(2, reader.stream_position()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_truncated_default_value() -> Result<()> {
+        let header = Header { kind: 7, length: 9 };
+        let mut buffer = encode(&header, Default::default())?;
+        buffer.truncate(4);
+        assert!(decode::<Header>(&buffer, Default::default()).is_err());
+
+        let options = Options {
+            on_read_error: ErrorPolicy::DefaultValue,
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(Cursor::new(&buffer), options);
+        assert!(reader.last_error().is_none());
+        let mut decoded = Header::default();
+        decoded.decode(&mut reader)?;
+        assert_eq!(Header { kind: 7, length: 0 }, decoded);
+        assert_eq!(
+            Some(io::ErrorKind::UnexpectedEof),
+            reader.last_error().map(|e| e.kind())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_matrix() -> Result<()> {
+        let data: Vec<f32> = (0..12).map(|i| i as f32 * 0.5).collect();
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(64, writer.write_matrix(3, 4, &data)?);
+        assert!(writer.write_matrix(4, 4, &data).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!((3, 4, data), reader.read_matrix::<f32>()?);
+
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u64(u64::MAX)?;
+        writer.write_u64(2)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_matrix::<f32>().is_err());
+
+        // Dimensions larger than the stream fail without preallocating
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u64(1 << 31)?;
+        writer.write_u64(1 << 31)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_matrix::<f32>().unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_wrapping() -> Result<()> {
+        let value = Wrapping(42u32);
+        let buffer = encode(&value, Default::default())?;
+        assert_eq!(42u32.to_le_bytes().to_vec(), buffer);
+        let decoded: Wrapping<u32> = decode(&buffer, Default::default())?;
+        assert_eq!(value, decoded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_restore() -> Result<()> {
+        let mut stream = Cursor::new(vec![0, 0, 0, 1, 0, 0, 0, 2]);
+        let mut reader = BinaryReader::new(&mut stream, Endian::Big.into());
+        assert_eq!(1, reader.read_u32()?);
+
+        let snapshot = reader.snapshot()?;
+        reader.options_mut().endian = Endian::Little;
+        assert_eq!(0x0200_0000, reader.read_u32()?);
+        reader.seek(SeekFrom::Start(0))?;
+
+        reader.restore(snapshot)?;
+        assert_eq!(Endian::Big, reader.options().endian);
+        assert_eq!(2, reader.read_u32()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_binary_heap() -> Result<()> {
+        let heap: BinaryHeap<u16> = [5, 1, 8, 3, 8].into_iter().collect();
+        let buffer = encode(&heap, Default::default())?;
+        let decoded: BinaryHeap<u16> = decode(&buffer, Default::default())?;
+        let prefix = encode(&Vec::<u16>::from([0; 5]), Default::default())?;
+        assert_eq!(prefix.len(), buffer.len());
+        assert_eq!(prefix[..buffer.len() - 10], buffer[..buffer.len() - 10]);
+        assert_eq!(heap.into_sorted_vec(), decoded.into_sorted_vec());
+
+        let options = Options {
+            max_buffer_size: Some(4),
+            ..Default::default()
+        };
+        assert!(decode::<BinaryHeap<u16>>(&buffer, options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn for_each_record() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        for (kind, body) in [
+            (1u16, vec![7u8, 0, 0, 0, 0xff]),
+            (2, vec![1, 2, 3]),
+            (1, vec![9, 0, 0, 0]),
+        ] {
+            writer.write_u16(kind)?;
+            writer.write_u32(body.len() as u32)?;
+            writer.write_bytes(body)?;
+        }
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let mut values = Vec::new();
+        reader.for_each_record(|kind, record| {
+            if kind == 1 {
+                values.push(record.read_u32()?);
+            }
+            Ok(())
+        })?;
+        assert_eq!(vec![7, 9], values);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_tracked() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(0)?;
+        let ranges = vec![
+            writer.write_tracked(&10u32)?,
+            writer.write_tracked(&Some(20u16))?,
+            writer.write_tracked(&30u64)?,
+        ];
+        assert_eq!(vec![1..5, 5..8, 8..16], ranges);
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(ranges[2].start))?;
+        assert_eq!(30, reader.read_u64()?);
 
-        writer.write_i64(temp)?;
+        Ok(())
+    }
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+    #[test]
+    fn read_trailer_length() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(1)?;
+        writer.write_u16(2)?;
+        assert_eq!(14, writer.finalize_with_trailer(true)?);
 
-        let read_temp = reader.read_i64()?;
-        assert_eq!(temp, read_temp);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(4))?;
+        assert_eq!(14, reader.read_trailer_length()?);
+        assert_eq!(2, reader.read_u16()?);
+
+        let mut reader =
+            BinaryReader::new(Cursor::new([0u8; 4]), Default::default());
+        assert!(reader.read_trailer_length().is_err());
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_i128() -> Result<()> {
-        let temp: i128 = i128::MAX;
+    fn read_footer() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u32(1)?;
+        assert_eq!(4, writer.write_footer(b"END!")?);
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_footer(b"END!")?;
+        assert_eq!(1, reader.read_u32()?);
+        let error = reader.read_footer(b"FIN!").unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        // Truncated stream is missing the footer
+        let mut reader = BinaryReader::new(
+            Cursor::new(&[1u8, 0, 0, 0, b'E', b'N'][..]),
+            Default::default(),
+        );
+        assert!(reader.read_footer(b"END!").is_err());
+        let mut reader =
+            BinaryReader::new(Cursor::new([b'N', b'D']), Default::default());
+        assert!(reader.read_footer(b"END!").is_err());
 
-        writer.write_i128(temp)?;
+        Ok(())
+    }
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+    #[test]
+    fn read_u32_or() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u16(1)?;
+        writer.write_u32(2)?;
+        writer.write_u8(3)?;
 
-        let read_temp = reader.read_i128()?;
-        assert_eq!(temp, read_temp);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(1, reader.read_u16()?);
+        assert_eq!(2, reader.read_u32_or(7)?);
+        // Truncated field is an error rather than the default
+        assert!(reader.read_u32_or(7).is_err());
+        reader.seek(SeekFrom::End(0))?;
+        assert_eq!(7, reader.read_u32_or(7)?);
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_i32() -> Result<()> {
-        let temp: i32 = i32::MAX;
+    fn read_write_sparse() -> Result<()> {
+        let mut items = vec![0i32; 1000];
+        items[3] = 30;
+        items[500] = -5;
+        items[999] = 9;
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(20, writer.write_sparse(&items)?);
 
-        writer.write_i32(temp)?;
+        let options = Options {
+            max_buffer_size: Some(1000),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(items, reader.read_sparse::<i32>()?);
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        // Length is bounded by the stream without max_buffer_size
+        reader.options_mut().max_buffer_size = None;
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_sparse::<i32>().is_err());
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_varint_u64(1 << 62)?;
+        writer.write_varint_u64(0)?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let error = reader.read_sparse::<i32>().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
 
-        let read_temp = reader.read_i32()?;
-        assert_eq!(temp, read_temp);
+        // Indices must be strictly increasing
+        let mut stream =
+            Cursor::new(vec![4, 2, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        assert!(reader.read_sparse::<i32>().is_err());
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_i16() -> Result<()> {
-        let temp: i16 = i16::MAX;
-
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
-
-        writer.write_i16(temp)?;
+    fn max_string_len() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string("text")?;
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        let options = Options {
+            max_string_len: Some(3),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_string().is_err());
 
-        let read_temp = reader.read_i16()?;
-        assert_eq!(temp, read_temp);
+        // Prefix is rejected before allocating or reading the body
+        let buffer = if cfg!(feature = "64bit") {
+            u64::MAX.to_le_bytes().to_vec()
+        } else {
+            u32::MAX.to_le_bytes().to_vec()
+        };
+        let options = Options {
+            max_string_len: Some(1024),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(Cursor::new(buffer), options);
+        let err = reader.read_string().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("max_string_len"));
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_i8() -> Result<()> {
-        let temp: i8 = i8::MAX;
+    fn roundtrip_stable_header() -> Result<()> {
+        let header = Header {
+            kind: 2,
+            length: 64,
+        };
+        let buffer = encode(&header, Default::default())?;
+        assert!(roundtrip_stable::<Header>(&buffer, Default::default())?);
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        // Any non-zero byte decodes as true but encodes as one
+        assert!(!roundtrip_stable::<bool>(&[2], Default::default())?);
 
-        writer.write_i8(temp)?;
+        Ok(())
+    }
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+    #[test]
+    fn encode_decode_vec() -> Result<()> {
+        let value = vec![1u32, 2, 3];
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        value.encode(&mut writer)?;
 
-        let read_temp = reader.read_i8()?;
-        assert_eq!(temp, read_temp);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let mut decoded: Vec<u32> = Vec::new();
+        decoded.decode(&mut reader)?;
+        assert_eq!(value, decoded);
+
+        let options = Options {
+            max_buffer_size: Some(2),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(Vec::<u32>::new().decode(&mut reader).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_u64() -> Result<()> {
-        let temp: u64 = u64::MAX;
+    fn read_f32_slice() -> Result<()> {
+        let values: Vec<f32> = (0..1000).map(|i| i as f32 / 4.0).collect();
+        for endian in [Endian::Little, Endian::Big] {
+            let mut stream = Cursor::new(Vec::new());
+            let mut writer = BinaryWriter::new(&mut stream, endian.into());
+            for value in &values {
+                writer.write_f32(value)?;
+            }
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+            let mut reader = BinaryReader::new(&mut stream, endian.into());
+            reader.seek(SeekFrom::Start(0))?;
+            assert_eq!(values, reader.read_f32_slice(values.len())?);
+        }
 
-        writer.write_u64(temp)?;
+        let options = Options {
+            max_buffer_size: Some(7),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(Cursor::new([0u8; 8]), options);
+        assert!(reader.read_u32_slice(2).is_err());
+        assert!(reader.read_u64_slice(usize::MAX).is_err());
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        Ok(())
+    }
 
-        let read_temp = reader.read_u64()?;
-        assert_eq!(temp, read_temp);
+    #[test]
+    fn read_write_record_field() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(8, writer.write_record_field(&[0xff, 0, 7], 8)?);
+        assert!(writer.write_record_field(&[1; 9], 8).is_err());
+        assert_eq!(8, writer.len()?);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(vec![0xff, 0, 7], reader.read_record_field(8)?);
+        assert_eq!(8, reader.stream_position()?);
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_u128() -> Result<()> {
-        let temp: u128 = u128::MAX;
+    fn read_write_float_bits() -> Result<()> {
+        let subnormal = f32::from_bits(1);
+        let nan = f64::from_bits(0x7ff8_0000_0000_0042);
+
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Endian::Big.into());
+        writer.write_f32(subnormal)?;
+        writer.write_f64(nan)?;
+        writer.write_f64(-f64::MIN_POSITIVE / 2.0)?;
+        assert_eq!(
+            vec![
+                0, 0, 0, 1, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0x42, 0x80, 0x08, 0,
+                0, 0, 0, 0, 0
+            ],
+            *writer.get_ref().get_ref()
+        );
+
+        let mut reader = BinaryReader::new(&mut stream, Endian::Big.into());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(1, reader.read_f32()?.to_bits());
+        assert_eq!(nan.to_bits(), reader.read_f64()?.to_bits());
+        assert_eq!(0x8008_0000_0000_0000, reader.read_f64()?.to_bits());
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        Ok(())
+    }
 
-        writer.write_u128(temp)?;
+    #[test]
+    fn read_write_list_suffix_count() -> Result<()> {
+        let items = vec![10u16, 20, 30];
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(1)?;
+        let written = writer.write_list_suffix_count(&items)?;
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        assert_eq!(6 + prefix, written);
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(1))?;
+        assert_eq!(items, reader.read_list_suffix_count::<u16>()?);
+        assert_eq!(reader.len()?, reader.stream_position()?);
 
-        let read_temp = reader.read_u128()?;
-        assert_eq!(temp, read_temp);
+        // Count does not match the elements before it
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.read_list_suffix_count::<u16>().is_err());
 
         Ok(())
     }
 
     #[test]
-    fn read_write_test_u32() -> Result<()> {
-        let temp: u32 = u32::MAX;
+    fn read_write_string_fixed() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        assert_eq!(11, writer.write_string_fixed("README.TXT", 11)?);
+        assert_eq!(4, writer.write_string_fixed("ÿ", 4)?);
+        assert!(writer.write_string_fixed("FILENAME.TXT", 11).is_err());
+        assert_eq!(15, writer.len()?);
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!("README.TXT", reader.read_string_fixed(11)?);
+        assert_eq!("ÿ", reader.read_string_fixed(4)?);
 
-        writer.write_u32(temp)?;
+        let mut reader =
+            BinaryReader::new(Cursor::new([0xff, 0]), Default::default());
+        assert!(reader.read_string_fixed(2).is_err());
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        Ok(())
+    }
 
-        let read_temp = reader.read_u32()?;
-        assert_eq!(temp, read_temp);
+    #[test]
+    fn read_narrowed() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u64(512)?;
+        writer.write_u64(70000)?;
+        writer.write_i32(-1)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(512, reader.read_u16_from_u64()?);
+        let error = reader.read_u16_from_u64().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert!(reader.read_narrowed::<i32, u8>().is_err());
 
         Ok(())
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn read_write_test_u16() -> Result<()> {
-        let temp: u16 = u16::MAX;
-
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+    fn derive_encode_decode() -> Result<()> {
+        #[derive(Debug, Default, Eq, PartialEq, Encodable, Decodable)]
+        struct Header<T> {
+            id: u32,
+            name: String,
+            #[binary(skip)]
+            cached: u64,
+            values: Vec<T>,
+        }
 
-        writer.write_u16(temp)?;
+        #[derive(Debug, Default, Eq, PartialEq, Encodable, Decodable)]
+        struct Pair(u8, Option<u16>);
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        #[derive(Debug, Default, Eq, PartialEq, Encodable, Decodable)]
+        struct Marker;
 
-        let read_temp = reader.read_u16()?;
-        assert_eq!(temp, read_temp);
+        let header = Header {
+            id: 7,
+            name: "header".to_owned(),
+            cached: 42,
+            values: vec![1u16, 2, 3],
+        };
+        let buffer = encode(&header, Default::default())?;
+        let decoded: Header<u16> = decode(&buffer, Default::default())?;
+        assert_eq!(7, decoded.id);
+        assert_eq!("header", decoded.name);
+        assert_eq!(0, decoded.cached);
+        assert_eq!(header.values, decoded.values);
 
-        Ok(())
-    }
+        let pair = Pair(1, Some(2));
+        let buffer = encode(&pair, Default::default())?;
+        assert_eq!(pair, decode(&buffer, Default::default())?);
 
-    #[test]
-    fn read_write_test_u8() -> Result<()> {
-        let temp: u8 = u8::MAX;
+        assert!(encode(&Marker, Default::default())?.is_empty());
+        assert_eq!(Marker, decode(&[], Default::default())?);
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        // Type parameters only need to be encodable
+        struct Id(u8);
 
-        writer.write_u8(temp)?;
+        impl Encodable for Id {
+            fn encode<W: Write + Seek>(
+                &self,
+                writer: &mut BinaryWriter<W>,
+            ) -> io::Result<()> {
+                writer.write_u8(self.0)?;
+                Ok(())
+            }
+        }
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+        #[derive(Encodable)]
+        struct Wrapper<T> {
+            value: T,
+        }
 
-        let read_temp = reader.read_u8()?;
-        assert_eq!(temp, read_temp);
+        let wrapper = Wrapper { value: Id(5) };
+        assert_eq!(vec![5], encode(&wrapper, Default::default())?);
 
         Ok(())
     }
 
+    #[cfg(feature = "derive")]
     #[test]
-    fn read_write_bytes() -> Result<()> {
-        let count = 20;
+    fn derive_option_bitmap() -> Result<()> {
+        #[derive(Debug, Default, Eq, PartialEq, Encodable, Decodable)]
+        #[binary(option_bitmap)]
+        struct Sparse {
+            id: u8,
+            a: Option<u8>,
+            b: Option<u16>,
+            c: Option<u8>,
+            d: Option<String>,
+            e: Option<u32>,
+        }
 
-        let temp = vec![16; count];
+        let value = Sparse {
+            id: 9,
+            a: Some(1),
+            b: None,
+            c: Some(3),
+            d: None,
+            e: Some(5),
+        };
+        let buffer = encode(&value, Default::default())?;
+        assert_eq!(vec![0b10101, 9, 1, 3, 5, 0, 0, 0], buffer);
+        assert_eq!(value, decode(&buffer, Default::default())?);
+
+        #[derive(Debug, Default, Eq, PartialEq, Encodable, Decodable)]
+        #[binary(option_bitmap)]
+        struct Generic<T> {
+            a: Option<T>,
+            b: u8,
+        }
 
-        let mut file = tempfile()?;
-        let mut writer = BinaryWriter::new(&mut file, Default::default());
+        let value = Generic {
+            a: Some(7u16),
+            b: 2,
+        };
+        let buffer = encode(&value, Default::default())?;
+        assert_eq!(vec![0b1, 7, 0, 2], buffer);
+        assert_eq!(value, decode(&buffer, Default::default())?);
 
-        writer.write_bytes(temp.clone())?;
+        Ok(())
+    }
 
-        writer.seek(SeekFrom::Start(0))?;
-        let mut reader = BinaryReader::new(&mut file, Default::default());
+    #[test]
+    fn read_write_endian_override() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u16(1)?;
+        writer.write_u32_endian(0x0a00_0001, Endian::Big)?;
+        writer.write_f64_endian(1.5, Endian::Big)?;
+        writer.write_u16(2)?;
+        assert_eq!(
+            vec![1, 0, 0x0a, 0, 0, 1, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 2, 0],
+            *writer.get_ref().get_ref()
+        );
 
-        let read_temp = reader.read_bytes(count)?;
-        assert_eq!(temp, read_temp);
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(1, reader.read_u16()?);
+        assert_eq!(0x0a00_0001, reader.read_u32_endian(Endian::Big)?);
+        assert_eq!(1.5, reader.read_f64_endian(Endian::Big)?);
+        assert_eq!(2, reader.read_u16()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_native_endian() -> Result<()> {
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer =
+            BinaryWriter::new(&mut stream, Endian::Native.into());
+        writer.write_u32(0x0102_0304)?;
+        writer.write_char('a')?;
+        assert_eq!(
+            [0x0102_0304u32.to_ne_bytes(), ('a' as u32).to_ne_bytes()]
+                .concat(),
+            *writer.get_ref().get_ref()
+        );
+        assert_eq!(Endian::native(), Endian::Native.resolve());
+
+        let mut reader =
+            BinaryReader::new(&mut stream, Endian::Native.into());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(0x0102_0304, reader.read_u32()?);
+        assert_eq!('a', reader.read_char()?);
 
         Ok(())
     }
@@ -1132,6 +5985,87 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn read_write_string_max_chars() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string_max_chars("ünïcödé", 7)?;
+        assert!(writer.write_string_max_chars("ünïcödé!", 7).is_err());
+        writer.write_string("ünïcödé!")?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!("ünïcödé", reader.read_string_max_chars(7)?);
+        assert!(reader.read_string_max_chars(7).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_string() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string("foo")?;
+        writer.write_optional_bytes(Some(&[0xc3, 0x28]))?;
+
+        let prefix = if cfg!(feature = "64bit") { 8 } else { 4 };
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(prefix + 3, reader.validate_string()?);
+        assert!(reader.validate_string().is_err());
+
+        // Sequences split across chunks are carried over
+        let value = format!("a{}", "é".repeat(600));
+        let mut stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_string(&value)?;
+        writer.write_optional_bytes(Some(&[0x61, 0xc3]))?;
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(prefix + value.len(), reader.validate_string()?);
+        assert!(reader.validate_string().is_err());
+
+        // Length is checked against the budget and max_buffer_size
+        let mut reader = BinaryReader::with_byte_budget(
+            &mut stream,
+            Default::default(),
+            prefix as u64 + 1,
+        );
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.validate_string().is_err());
+        let options = Options {
+            max_buffer_size: Some(16),
+            ..Default::default()
+        };
+        let mut reader = BinaryReader::new(&mut stream, options);
+        reader.seek(SeekFrom::Start(0))?;
+        assert!(reader.validate_string().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_write_presence() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_presence(Presence::Present)?;
+        writer.write_u16(16)?;
+        writer.write_presence(Presence::Absent(3))?;
+        assert!(writer.write_presence(Presence::Absent(0)).is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(Presence::Present, reader.read_presence()?);
+        assert_eq!(16, reader.read_u16()?);
+        assert_eq!(Presence::Absent(3), reader.read_presence()?);
+
+        Ok(())
+    }
+
     #[test]
     fn read_write_test_bool() -> Result<()> {
         let positive = true;
@@ -1214,6 +6148,25 @@ mod tests {
     }
     */
 
+    #[test]
+    fn write_to_memorystream_into_inner() -> Result<()> {
+        let stream = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(stream, Default::default());
+        writer.write_f32(1.0)?;
+        assert_eq!(4, writer.get_ref().get_ref().len());
+        writer.get_mut().get_mut().push(0);
+
+        let buffer = writer.into_inner().into_inner();
+        assert_eq!(vec![0, 0, 0x80, 0x3f, 0], buffer);
+
+        let mut reader =
+            BinaryReader::new(Cursor::new(buffer), Default::default());
+        assert_eq!(1.0, reader.read_f32()?);
+        assert_eq!(4, reader.get_ref().position());
+        assert_eq!(5, reader.into_inner().into_inner().len());
+        Ok(())
+    }
+
     #[test]
     fn write_to_filestream_overlapping() -> Result<()> {
         let mut file = tempfile()?;
@@ -0,0 +1,168 @@
+//! Count stream operations to find hot paths when profiling.
+use std::io::{Read, Result, Seek, SeekFrom, Write};
+
+/// Counts of the operations on a stream.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// Number of calls to `read`.
+    pub reads: u64,
+    /// Number of calls to `write`.
+    pub writes: u64,
+    /// Number of calls to `seek`.
+    pub seeks: u64,
+    /// Number of calls to `flush`.
+    pub flushes: u64,
+    /// Total bytes read.
+    pub bytes_read: u64,
+    /// Total bytes written.
+    pub bytes_written: u64,
+}
+
+/// Reader that counts the operations on an inner stream.
+///
+/// Each primitive read is a separate read from the stream so
+/// a high `reads` count relative to `bytes_read` suggests a
+/// decoder should use a bulk method instead.
+pub struct CountingReader<R> {
+    inner: R,
+    stats: Stats,
+}
+
+impl<R> CountingReader<R> {
+    /// Create a counting reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Get the counts so far.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Reset the counts to zero.
+    pub fn reset(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Consume this reader and return the inner stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.stats.reads += 1;
+        self.stats.bytes_read += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.stats.seeks += 1;
+        self.inner.seek(pos)
+    }
+}
+
+/// Writer that counts the operations on an inner stream.
+pub struct CountingWriter<W> {
+    inner: W,
+    stats: Stats,
+}
+
+impl<W> CountingWriter<W> {
+    /// Create a counting writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Get the counts so far.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Reset the counts to zero.
+    pub fn reset(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Consume this writer and return the inner stream.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.stats.writes += 1;
+        self.stats.bytes_written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stats.flushes += 1;
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for CountingWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.stats.seeks += 1;
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountingReader, CountingWriter, Stats};
+    use crate::{BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use std::io::Cursor;
+
+    #[test]
+    fn counting_reader_writer() -> Result<()> {
+        let mut writer = BinaryWriter::new(
+            CountingWriter::new(Cursor::new(Vec::new())),
+            Default::default(),
+        );
+        writer.write_u8(1)?;
+        writer.write_u32(2)?;
+        writer.write_bytes([3u8; 8])?;
+        writer.flush()?;
+        let stream = writer.into_inner();
+        assert_eq!(
+            Stats {
+                writes: 3,
+                flushes: 1,
+                bytes_written: 13,
+                ..Default::default()
+            },
+            stream.stats()
+        );
+
+        let buffer = stream.into_inner().into_inner();
+        let mut reader = BinaryReader::new(
+            CountingReader::new(Cursor::new(buffer)),
+            Default::default(),
+        );
+        for _ in 0..5 {
+            reader.read_u8()?;
+        }
+        reader.read_bytes(8)?;
+        let stats = reader.get_ref().stats();
+        assert_eq!(6, stats.reads);
+        assert_eq!(13, stats.bytes_read);
+        assert_eq!(0, stats.writes);
+
+        Ok(())
+    }
+}
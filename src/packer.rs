@@ -0,0 +1,138 @@
+//! Pack small fields into a single byte.
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use crate::{BinaryReader, BinaryWriter};
+
+/// Packs flags and small fields into a single byte.
+///
+/// Fields are packed from the most significant bit so the first
+/// field is in the high bits of the byte.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BytePacker {
+    value: u8,
+    used: u32,
+    overflow: bool,
+}
+
+impl BytePacker {
+    /// Create an empty packer.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a single bit flag.
+    pub fn bit(self, flag: bool) -> Self {
+        self.bits(flag as u8, 1)
+    }
+
+    /// Add a field using `width` bits.
+    pub fn bits(mut self, value: u8, width: u32) -> Self {
+        self.used += width;
+        if width > 8 || self.used > 8 || (value as u32) >> width != 0 {
+            self.overflow = true;
+            return self;
+        }
+        self.value |= value << (8 - self.used);
+        self
+    }
+
+    /// Get the packed byte.
+    ///
+    /// Errors if the fields exceed eight bits or a value does not
+    /// fit in the width of the field.
+    pub fn finish(&self) -> Result<u8> {
+        if self.overflow {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "packed fields do not fit in a byte",
+            ));
+        }
+        Ok(self.value)
+    }
+
+    /// Write the packed byte.
+    pub fn write<W: Write + Seek>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+    ) -> Result<usize> {
+        writer.write_u8(self.finish()?)
+    }
+}
+
+/// Unpacks fields written using a `BytePacker` in the same order
+/// they were packed.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteUnpacker {
+    value: u8,
+    used: u32,
+}
+
+impl ByteUnpacker {
+    /// Create an unpacker for a byte.
+    pub fn new(value: u8) -> Self {
+        Self { value, used: 0 }
+    }
+
+    /// Read a byte to unpack.
+    pub fn read<R: Read + Seek>(
+        reader: &mut BinaryReader<R>,
+    ) -> Result<Self> {
+        Ok(Self::new(reader.read_u8()?))
+    }
+
+    /// Get the next single bit flag.
+    pub fn bit(&mut self) -> Result<bool> {
+        Ok(self.bits(1)? == 1)
+    }
+
+    /// Get the next field of `width` bits.
+    ///
+    /// Errors if the fields exceed eight bits.
+    pub fn bits(&mut self, width: u32) -> Result<u8> {
+        if width == 0 {
+            return Ok(0);
+        }
+        if width > 8 || self.used + width > 8 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "unpacked fields exceed a byte",
+            ));
+        }
+        self.used += width;
+        let mask = (1u16 << width) - 1;
+        Ok(((self.value as u16 >> (8 - self.used)) & mask) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BytePacker, ByteUnpacker};
+    use crate::{BinaryReader, BinaryWriter};
+    use anyhow::Result;
+    use std::io::{Cursor, SeekFrom};
+
+    #[test]
+    fn pack_unpack_byte() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        BytePacker::new()
+            .bit(true)
+            .bits(2, 2)
+            .bits(17, 5)
+            .write(&mut writer)?;
+        assert!(BytePacker::new().bits(4, 2).finish().is_err());
+        assert!(BytePacker::new().bits(1, 5).bits(1, 4).finish().is_err());
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let mut unpacker = ByteUnpacker::read(&mut reader)?;
+        assert!(unpacker.bit()?);
+        assert_eq!(2, unpacker.bits(2)?);
+        assert_eq!(17, unpacker.bits(5)?);
+        assert!(unpacker.bit().is_err());
+        assert_eq!(vec![0b1101_0001], buffer);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Adapter for reading from streams that do not support seeking.
+use std::io::{Cursor, Error, Read, Result, Seek, SeekFrom};
+
+/// Buffers a non-seekable reader into memory so that it can be
+/// used with a `BinaryReader`.
+///
+/// The entire source is read into memory when the buffer is
+/// created so the memory cost is the full length of the source;
+/// use `with_limit` to cap the number of bytes that will be
+/// buffered when reading from untrusted sources such as sockets
+/// or pipes.
+pub struct SeekableBuffer {
+    inner: Cursor<Vec<u8>>,
+}
+
+impl SeekableBuffer {
+    /// Read all the bytes from a reader into a seekable buffer.
+    pub fn new<R: Read>(mut reader: R) -> Result<Self> {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        Ok(Self {
+            inner: Cursor::new(buffer),
+        })
+    }
+
+    /// Read all the bytes from a reader into a seekable buffer
+    /// and error if the reader yields more than `limit` bytes.
+    pub fn with_limit<R: Read>(reader: R, limit: usize) -> Result<Self> {
+        let mut buffer = Vec::new();
+        reader
+            .take((limit as u64).saturating_add(1))
+            .read_to_end(&mut buffer)?;
+        if buffer.len() > limit {
+            return Err(Error::other(format!(
+                "length exceeds seekable buffer limit {}",
+                limit
+            )));
+        }
+        Ok(Self {
+            inner: Cursor::new(buffer),
+        })
+    }
+
+    /// Get the buffered bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.get_ref()
+    }
+
+    /// Consume this buffer and return the buffered bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.inner.into_inner()
+    }
+}
+
+impl Read for SeekableBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for SeekableBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeekableBuffer;
+    use crate::{
+        decode_stream, encode, BinaryReader, BinaryWriter, Decodable,
+        Encodable,
+    };
+    use anyhow::Result;
+    use std::io::{self, Error, Read, Seek, SeekFrom, Write};
+
+    #[derive(Debug, Default, Eq, PartialEq)]
+    struct Entry([u8; 4], Vec<u8>);
+
+    impl Encodable for Entry {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            writer.write_bytes(self.0)?;
+
+            let size_pos = writer.stream_position()?;
+            writer.write_u32(0)?;
+            self.1.encode(&mut *writer)?;
+
+            let row_pos = writer.stream_position()?;
+            let row_len = row_pos - (size_pos + 4);
+            writer.seek(SeekFrom::Start(size_pos))?;
+            writer.write_u32(row_len as u32)?;
+            writer.seek(SeekFrom::Start(row_pos))?;
+            Ok(())
+        }
+    }
+
+    impl Decodable for Entry {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            self.0 = reader.read_bytes(4)?.try_into().unwrap();
+
+            let row_len = reader.read_u32()? as u64;
+            let row_pos = reader.stream_position()?;
+            self.1.decode(&mut *reader)?;
+            if reader.stream_position()? - row_pos != row_len {
+                return Err(Error::other("row length mismatch"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn seekable_buffer_decode() -> Result<()> {
+        let entry = Entry([1, 2, 3, 4], vec![5, 6, 7, 8]);
+        let buffer = encode(&entry, Default::default())?;
+
+        // A byte slice implements `Read` but not `Seek`
+        let mut stream = SeekableBuffer::new(buffer.as_slice())?;
+        let decoded: Entry = decode_stream(&mut stream, Default::default())?;
+        assert_eq!(entry, decoded);
+
+        assert!(SeekableBuffer::with_limit(buffer.as_slice(), 4).is_err());
+        let stream =
+            SeekableBuffer::with_limit(buffer.as_slice(), buffer.len())?;
+        assert_eq!(buffer, stream.into_inner());
+
+        Ok(())
+    }
+}
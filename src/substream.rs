@@ -0,0 +1,212 @@
+//! Stream bounded to a range of an inner stream.
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+#[cfg(feature = "async")]
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+#[cfg(all(feature = "async", not(feature = "tokio")))]
+use futures::io::{AsyncRead, AsyncSeek};
+#[cfg(all(feature = "async", feature = "tokio"))]
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// Bounds reads and seeks to a range of an inner stream.
+///
+/// Positions are relative to the start of the range and reads
+/// never consume bytes beyond the end of the range.
+pub struct SubStream<S> {
+    inner: S,
+    start: u64,
+    length: u64,
+    position: u64,
+}
+
+impl<S: Seek> SubStream<S> {
+    /// Create a sub stream of `length` bytes starting at the
+    /// current position of the inner stream.
+    pub fn new(mut inner: S, length: u64) -> Result<Self> {
+        let start = inner.stream_position()?;
+        Ok(Self {
+            inner,
+            start,
+            length,
+            position: 0,
+        })
+    }
+
+    /// Length of the range.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    /// Determine if the range is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Consume this sub stream and return the inner stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> SubStream<S> {
+    /// Create a sub stream of `length` bytes starting at `start`
+    /// when the inner stream is already positioned at `start`.
+    #[cfg(feature = "async")]
+    pub(crate) fn from_parts(inner: S, start: u64, length: u64) -> Self {
+        Self {
+            inner,
+            start,
+            length,
+            position: 0,
+        }
+    }
+
+    /// Number of bytes that may be read into a buffer.
+    fn read_limit(&self, len: usize) -> usize {
+        let remaining = self.length.saturating_sub(self.position);
+        remaining.min(len as u64) as usize
+    }
+
+    /// Position in the range for a seek.
+    fn seek_target(&self, pos: SeekFrom) -> Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => {
+                self.position.checked_add_signed(offset)
+            }
+        };
+        target.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })
+    }
+}
+
+impl<S: Read + Seek> Read for SubStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let max = self.read_limit(buf.len());
+        if max == 0 {
+            return Ok(0);
+        }
+        let read = self.inner.read(&mut buf[..max])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<S: Seek> Seek for SubStream<S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let target = self.seek_target(pos)?;
+        self.inner.seek(SeekFrom::Start(self.start + target))?;
+        self.position = target;
+        Ok(target)
+    }
+}
+
+#[cfg(all(feature = "async", not(feature = "tokio")))]
+impl<S: AsyncRead + Unpin> AsyncRead for SubStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let max = self.read_limit(buf.len());
+        if max == 0 {
+            return Poll::Ready(Ok(0));
+        }
+        let read =
+            ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf[..max]))?;
+        self.position += read as u64;
+        Poll::Ready(Ok(read))
+    }
+}
+
+#[cfg(all(feature = "async", not(feature = "tokio")))]
+impl<S: AsyncSeek + Unpin> AsyncSeek for SubStream<S> {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64>> {
+        let target = self.seek_target(pos)?;
+        let start = self.start;
+        ready!(Pin::new(&mut self.inner)
+            .poll_seek(cx, SeekFrom::Start(start + target)))?;
+        self.position = target;
+        Poll::Ready(Ok(target))
+    }
+}
+
+#[cfg(all(feature = "async", feature = "tokio"))]
+impl<S: AsyncRead + Unpin> AsyncRead for SubStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let max = self.read_limit(buf.remaining());
+        if max == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let mut limited = ReadBuf::new(buf.initialize_unfilled_to(max));
+        ready!(Pin::new(&mut self.inner).poll_read(cx, &mut limited))?;
+        let read = limited.filled().len();
+        buf.advance(read);
+        self.position += read as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(all(feature = "async", feature = "tokio"))]
+impl<S: AsyncSeek + Unpin> AsyncSeek for SubStream<S> {
+    fn start_seek(mut self: Pin<&mut Self>, pos: SeekFrom) -> Result<()> {
+        let target = self.seek_target(pos)?;
+        let start = self.start;
+        Pin::new(&mut self.inner)
+            .start_seek(SeekFrom::Start(start + target))?;
+        self.position = target;
+        Ok(())
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<u64>> {
+        ready!(Pin::new(&mut self.inner).poll_complete(cx))?;
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubStream;
+    use anyhow::Result;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    #[test]
+    fn sub_stream_bounds() -> Result<()> {
+        let mut stream = Cursor::new(vec![0u8, 1, 2, 3, 4, 5, 6, 7]);
+        stream.seek(SeekFrom::Start(2))?;
+
+        let mut sub = SubStream::new(&mut stream, 4)?;
+        let mut buffer = Vec::new();
+        sub.read_to_end(&mut buffer)?;
+        assert_eq!(vec![2, 3, 4, 5], buffer);
+
+        assert_eq!(1, sub.seek(SeekFrom::Start(1))?);
+        assert_eq!(3, sub.seek(SeekFrom::End(-1))?);
+        let mut byte = [0u8; 1];
+        sub.read_exact(&mut byte)?;
+        assert_eq!([5], byte);
+        assert!(sub.read_exact(&mut byte).is_err());
+        assert!(sub.seek(SeekFrom::Current(-5)).is_err());
+
+        Ok(())
+    }
+}
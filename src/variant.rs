@@ -0,0 +1,665 @@
+//! Decode tagged variants using a table defined at runtime.
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Error, ErrorKind, Read, Result, Seek, Write},
+};
+
+use crate::{BinaryReader, BinaryWriter};
+
+/// Kinds for the tag that prefixes a variant.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TagKind {
+    /// Tag is a `u8`.
+    U8,
+    /// Tag is a `u16`.
+    U16,
+    /// Tag is a `u32`.
+    U32,
+    /// Tag is a `u64`.
+    U64,
+    /// Tag is a four character code such as `RIFF`.
+    ///
+    /// The four bytes are compared as a big endian `u32`
+    /// regardless of the endian for the stream, use `fourcc` to
+    /// get the tag for a code.
+    FourCC,
+}
+
+/// Get the tag for a four character code.
+pub const fn fourcc(code: &[u8; 4]) -> u64 {
+    u32::from_be_bytes(*code) as u64
+}
+
+/// Primitive kinds for the fields of a variant.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FieldKind {
+    /// Field is a `u8`.
+    U8,
+    /// Field is a `u16`.
+    U16,
+    /// Field is a `u32`.
+    U32,
+    /// Field is a `u64`.
+    U64,
+    /// Field is a `u128`.
+    U128,
+    /// Field is an `i8`.
+    I8,
+    /// Field is an `i16`.
+    I16,
+    /// Field is an `i32`.
+    I32,
+    /// Field is an `i64`.
+    I64,
+    /// Field is an `i128`.
+    I128,
+    /// Field is a `f32`.
+    F32,
+    /// Field is a `f64`.
+    F64,
+    /// Field is a `bool`.
+    Bool,
+    /// Field is a `char`.
+    Char,
+    /// Field is a length-prefixed `String`.
+    String,
+}
+
+/// Value decoded using a `VariantTable`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedValue {
+    /// A `u8` value.
+    U8(u8),
+    /// A `u16` value.
+    U16(u16),
+    /// A `u32` value.
+    U32(u32),
+    /// A `u64` value.
+    U64(u64),
+    /// A `u128` value.
+    U128(u128),
+    /// An `i8` value.
+    I8(i8),
+    /// An `i16` value.
+    I16(i16),
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// An `i128` value.
+    I128(i128),
+    /// A `f32` value.
+    F32(f32),
+    /// A `f64` value.
+    F64(f64),
+    /// A `bool` value.
+    Bool(bool),
+    /// A `char` value.
+    Char(char),
+    /// A `String` value.
+    String(String),
+    /// A variant and the values for its fields.
+    Variant {
+        /// Tag for the variant.
+        tag: u64,
+        /// Decoded field values.
+        fields: Vec<DecodedValue>,
+    },
+}
+
+/// Variant with an unrecognized tag whose size-prefixed body
+/// is preserved so it can be re-encoded byte for byte.
+///
+/// Encoders that want decoders to be able to skip unknown variants
+/// should write the body of every variant using `write_sized`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct UnknownVariant {
+    /// Tag for the variant.
+    pub tag: u64,
+    /// Raw bytes for the body of the variant.
+    pub data: Vec<u8>,
+}
+
+/// Maps tag values to the fields for each variant.
+#[derive(Debug, Clone)]
+pub struct VariantTable {
+    tag: TagKind,
+    variants: HashMap<u64, Vec<FieldKind>>,
+}
+
+impl VariantTable {
+    /// Create an empty variant table.
+    pub fn new(tag: TagKind) -> Self {
+        Self {
+            tag,
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Add the fields for a variant tag.
+    pub fn insert(&mut self, tag: u64, fields: Vec<FieldKind>) {
+        self.variants.insert(tag, fields);
+    }
+
+    /// Get the fields for a variant tag.
+    pub fn get(&self, tag: u64) -> Option<&[FieldKind]> {
+        self.variants.get(&tag).map(|fields| fields.as_slice())
+    }
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Read a tag and then the fields for the variant
+    /// described by the table.
+    pub fn read_variant(
+        &mut self,
+        table: &VariantTable,
+    ) -> Result<DecodedValue> {
+        let tag = self.read_discriminant(table.tag)?;
+        let kinds = table.get(tag).ok_or_else(|| {
+            Error::other(format!("unknown variant tag {}", tag))
+        })?;
+        let mut fields = Vec::with_capacity(kinds.len());
+        for kind in kinds {
+            fields.push(self.read_value(*kind)?);
+        }
+        Ok(DecodedValue::Variant { tag, fields })
+    }
+
+    /// Read a tag of the given kind.
+    pub fn read_discriminant(&mut self, kind: TagKind) -> Result<u64> {
+        Ok(match kind {
+            TagKind::U8 => self.read_u8()? as u64,
+            TagKind::U16 => self.read_u16()? as u64,
+            TagKind::U32 => self.read_u32()? as u64,
+            TagKind::U64 => self.read_u64()?,
+            TagKind::FourCC => {
+                let mut code = [0u8; 4];
+                self.fill_buffer(&mut code)?;
+                fourcc(&code)
+            }
+        })
+    }
+
+    /// Read a tag of the given kind without consuming it so the
+    /// caller can choose a decoder that reads the tag again.
+    pub fn peek_discriminant(&mut self, kind: TagKind) -> Result<u64> {
+        let position = self.stream_position()?;
+        self.read_at(position, |reader| reader.read_discriminant(kind))
+    }
+
+    /// Read tag-length-value fields until the end of the stream
+    /// and collect the raw values by tag.
+    ///
+    /// When a tag is repeated the last value is kept.
+    pub fn read_tlv_map(
+        &mut self,
+        tag: TagKind,
+        length: TagKind,
+    ) -> Result<BTreeMap<u64, Vec<u8>>> {
+        let end = self.len()?;
+        let mut fields = BTreeMap::new();
+        while self.stream_position()? < end {
+            let key = self.read_discriminant(tag)?;
+            let size = self.read_discriminant(length)?;
+            fields.insert(key, self.read_buffer(size as usize)?);
+        }
+        Ok(fields)
+    }
+
+    /// Read a value of the given kind.
+    pub fn read_value(&mut self, kind: FieldKind) -> Result<DecodedValue> {
+        Ok(match kind {
+            FieldKind::U8 => DecodedValue::U8(self.read_u8()?),
+            FieldKind::U16 => DecodedValue::U16(self.read_u16()?),
+            FieldKind::U32 => DecodedValue::U32(self.read_u32()?),
+            FieldKind::U64 => DecodedValue::U64(self.read_u64()?),
+            FieldKind::U128 => DecodedValue::U128(self.read_u128()?),
+            FieldKind::I8 => DecodedValue::I8(self.read_i8()?),
+            FieldKind::I16 => DecodedValue::I16(self.read_i16()?),
+            FieldKind::I32 => DecodedValue::I32(self.read_i32()?),
+            FieldKind::I64 => DecodedValue::I64(self.read_i64()?),
+            FieldKind::I128 => DecodedValue::I128(self.read_i128()?),
+            FieldKind::F32 => DecodedValue::F32(self.read_f32()?),
+            FieldKind::F64 => DecodedValue::F64(self.read_f64()?),
+            FieldKind::Bool => DecodedValue::Bool(self.read_bool()?),
+            FieldKind::Char => DecodedValue::Char(self.read_char()?),
+            FieldKind::String => DecodedValue::String(self.read_string()?),
+        })
+    }
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Read the size-prefixed body of a variant with an unrecognized
+    /// tag that has already been read from the stream.
+    pub fn read_unknown_variant(
+        &mut self,
+        tag: u64,
+    ) -> Result<UnknownVariant> {
+        let length = if cfg!(feature = "64bit") {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        let data = self.read_buffer(length as usize)?;
+        Ok(UnknownVariant { tag, data })
+    }
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Write a tag of the given kind.
+    ///
+    /// Errors if the tag does not fit in the kind.
+    pub fn write_discriminant(
+        &mut self,
+        kind: TagKind,
+        tag: u64,
+    ) -> Result<usize> {
+        let overflow = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("tag {} does not fit in {:?}", tag, kind),
+            )
+        };
+        match kind {
+            TagKind::U8 => {
+                self.write_u8(u8::try_from(tag).map_err(|_| overflow())?)
+            }
+            TagKind::U16 => {
+                self.write_u16(u16::try_from(tag).map_err(|_| overflow())?)
+            }
+            TagKind::U32 => {
+                self.write_u32(u32::try_from(tag).map_err(|_| overflow())?)
+            }
+            TagKind::U64 => self.write_u64(tag),
+            TagKind::FourCC => {
+                let code = u32::try_from(tag).map_err(|_| overflow())?;
+                self.stream.write_all(&code.to_be_bytes())?;
+                Ok(4)
+            }
+        }
+    }
+
+    /// Write a decoded value.
+    ///
+    /// Variants cannot be written as the representation of the
+    /// tag is not known.
+    pub fn write_value(&mut self, value: &DecodedValue) -> Result<usize> {
+        match value {
+            DecodedValue::U8(value) => self.write_u8(value),
+            DecodedValue::U16(value) => self.write_u16(value),
+            DecodedValue::U32(value) => self.write_u32(value),
+            DecodedValue::U64(value) => self.write_u64(value),
+            DecodedValue::U128(value) => self.write_u128(value),
+            DecodedValue::I8(value) => self.write_i8(value),
+            DecodedValue::I16(value) => self.write_i16(value),
+            DecodedValue::I32(value) => self.write_i32(value),
+            DecodedValue::I64(value) => self.write_i64(value),
+            DecodedValue::I128(value) => self.write_i128(value),
+            DecodedValue::F32(value) => self.write_f32(value),
+            DecodedValue::F64(value) => self.write_f64(value),
+            DecodedValue::Bool(value) => self.write_bool(value),
+            DecodedValue::Char(value) => self.write_char(value),
+            DecodedValue::String(value) => self.write_string(value),
+            DecodedValue::Variant { .. } => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot write a variant value without a tag kind",
+            )),
+        }
+    }
+
+    /// Write the size-prefixed body of an unknown variant.
+    ///
+    /// The tag is not written so that callers can encode it using
+    /// the same representation as the known variants.
+    pub fn write_unknown_variant(
+        &mut self,
+        variant: &UnknownVariant,
+    ) -> Result<u64> {
+        self.write_sized(|writer| writer.stream.write_all(&variant.data))
+    }
+}
+
+/// Convert the values in a stream between endian orders.
+///
+/// Values are read according to the schema using the options for
+/// the reader and written using the options for the writer; the
+/// schema is repeated until the end of the reader is reached.
+pub fn convert_endian<R, W>(
+    reader: &mut BinaryReader<R>,
+    writer: &mut BinaryWriter<W>,
+    schema: &[FieldKind],
+) -> Result<()>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    if schema.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "schema must not be empty",
+        ));
+    }
+    let length = reader.len()?;
+    while reader.stream_position()? < length {
+        for kind in schema {
+            let value = reader.read_value(*kind)?;
+            writer.write_value(&value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        convert_endian, fourcc, DecodedValue, FieldKind, TagKind,
+        UnknownVariant, VariantTable,
+    };
+    use crate::{
+        decode, encode, BinaryReader, BinaryWriter, Decodable, Encodable,
+        Endian,
+    };
+    use anyhow::Result;
+    use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Message {
+        Ping(u32),
+        Unknown(UnknownVariant),
+    }
+
+    impl Default for Message {
+        fn default() -> Self {
+            Self::Ping(0)
+        }
+    }
+
+    impl Encodable for Message {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            match self {
+                Self::Ping(value) => {
+                    writer.write_u8(1)?;
+                    writer.write_sized(|writer| {
+                        writer.write_u32(value)?;
+                        Ok(())
+                    })?;
+                }
+                Self::Unknown(variant) => {
+                    writer.write_u8(variant.tag as u8)?;
+                    writer.write_unknown_variant(variant)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Decodable for Message {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            *self = match reader.read_u8()? {
+                1 => {
+                    let mut body = reader.read_sized()?;
+                    Self::Ping(body.read_u32()?)
+                }
+                tag => {
+                    Self::Unknown(reader.read_unknown_variant(tag as u64)?)
+                }
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn decode_unknown_variant() -> Result<()> {
+        let ping = Message::Ping(32);
+        let buffer = encode(&ping, Default::default())?;
+        assert_eq!(ping, decode::<Message>(&buffer, Default::default())?);
+
+        // Variant from a newer version of the protocol
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(9)?;
+        writer.write_sized(|writer| {
+            writer.write_string("future")?;
+            writer.write_u64(64)?;
+            Ok(())
+        })?;
+
+        let message: Message = decode(&buffer, Default::default())?;
+        assert!(matches!(
+            &message,
+            Message::Unknown(UnknownVariant { tag: 9, .. })
+        ));
+        assert_eq!(buffer, encode(&message, Default::default())?);
+
+        Ok(())
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Shape {
+        Rect(u32, u32),
+        Circle(u32),
+        Unknown(UnknownVariant),
+    }
+
+    impl Default for Shape {
+        fn default() -> Self {
+            Self::Circle(0)
+        }
+    }
+
+    const RECT: u64 = fourcc(b"RECT");
+    const CIRC: u64 = fourcc(b"CIRC");
+
+    impl Encodable for Shape {
+        fn encode<W: Write + Seek>(
+            &self,
+            writer: &mut BinaryWriter<W>,
+        ) -> io::Result<()> {
+            match self {
+                Self::Rect(width, height) => {
+                    writer.write_discriminant(TagKind::FourCC, RECT)?;
+                    writer.write_sized(|writer| {
+                        writer.write_u32(width)?;
+                        writer.write_u32(height)?;
+                        Ok(())
+                    })?;
+                }
+                Self::Circle(radius) => {
+                    writer.write_discriminant(TagKind::FourCC, CIRC)?;
+                    writer.write_sized(|writer| {
+                        writer.write_u32(radius)?;
+                        Ok(())
+                    })?;
+                }
+                Self::Unknown(variant) => {
+                    writer
+                        .write_discriminant(TagKind::FourCC, variant.tag)?;
+                    writer.write_unknown_variant(variant)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Decodable for Shape {
+        fn decode<R: Read + Seek>(
+            &mut self,
+            reader: &mut BinaryReader<R>,
+        ) -> io::Result<()> {
+            *self = match reader.read_discriminant(TagKind::FourCC)? {
+                RECT => {
+                    let mut body = reader.read_sized()?;
+                    Self::Rect(body.read_u32()?, body.read_u32()?)
+                }
+                CIRC => {
+                    let mut body = reader.read_sized()?;
+                    Self::Circle(body.read_u32()?)
+                }
+                tag => Self::Unknown(reader.read_unknown_variant(tag)?),
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fourcc_tagged_enum() -> Result<()> {
+        for shape in [Shape::Rect(4, 3), Shape::Circle(2)] {
+            let buffer = encode(&shape, Endian::Big.into())?;
+            assert_eq!(shape, decode::<Shape>(&buffer, Endian::Big.into())?);
+        }
+        let buffer = encode(&Shape::Circle(2), Default::default())?;
+        assert_eq!(b"CIRC", &buffer[..4]);
+
+        let unknown = Shape::Unknown(UnknownVariant {
+            tag: fourcc(b"OVAL"),
+            data: vec![1, 2],
+        });
+        let buffer = encode(&unknown, Default::default())?;
+        assert_eq!(unknown, decode::<Shape>(&buffer, Default::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_variant_table() -> Result<()> {
+        let mut table = VariantTable::new(TagKind::U8);
+        table.insert(1, vec![FieldKind::U32, FieldKind::String]);
+        table.insert(2, vec![FieldKind::Bool, FieldKind::I16]);
+
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(2)?;
+        writer.write_bool(true)?;
+        writer.write_i16(-16)?;
+        writer.write_u8(1)?;
+        writer.write_u32(32)?;
+        writer.write_string("foo")?;
+        writer.write_u8(3)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+
+        let value = reader.read_variant(&table)?;
+        assert_eq!(
+            DecodedValue::Variant {
+                tag: 2,
+                fields: vec![
+                    DecodedValue::Bool(true),
+                    DecodedValue::I16(-16)
+                ],
+            },
+            value
+        );
+
+        let value = reader.read_variant(&table)?;
+        assert_eq!(
+            DecodedValue::Variant {
+                tag: 1,
+                fields: vec![
+                    DecodedValue::U32(32),
+                    DecodedValue::String(String::from("foo"))
+                ],
+            },
+            value
+        );
+
+        assert!(reader.read_variant(&table).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn peek_discriminant() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u16(2)?;
+        writer.write_bool(true)?;
+        writer.write_i16(-16)?;
+
+        let mut table = VariantTable::new(TagKind::U16);
+        table.insert(2, vec![FieldKind::Bool, FieldKind::I16]);
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        assert_eq!(2, reader.peek_discriminant(TagKind::U16)?);
+        assert_eq!(0, reader.stream_position()?);
+        assert_eq!(
+            DecodedValue::Variant {
+                tag: 2,
+                fields: vec![
+                    DecodedValue::Bool(true),
+                    DecodedValue::I16(-16)
+                ],
+            },
+            reader.read_variant(&table)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_tlv_map() -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut stream = Cursor::new(&mut buffer);
+        let mut writer = BinaryWriter::new(&mut stream, Default::default());
+        writer.write_u8(1)?;
+        writer.write_u16(3)?;
+        writer.write_bytes(b"foo")?;
+        writer.write_u8(7)?;
+        writer.write_u16(0)?;
+        writer.write_u8(2)?;
+        writer.write_u16(2)?;
+        writer.write_u16(16)?;
+
+        let mut reader = BinaryReader::new(&mut stream, Default::default());
+        reader.seek(SeekFrom::Start(0))?;
+        let fields = reader.read_tlv_map(TagKind::U8, TagKind::U16)?;
+        assert_eq!(3, fields.len());
+        assert_eq!(Some(&b"foo".to_vec()), fields.get(&1));
+        assert_eq!(Some(&vec![16, 0]), fields.get(&2));
+        assert_eq!(Some(&Vec::new()), fields.get(&7));
+
+        Ok(())
+    }
+
+    #[test]
+    fn convert_endian_schema() -> Result<()> {
+        let mut input = Cursor::new(Vec::new());
+        let mut writer = BinaryWriter::new(&mut input, Endian::Big.into());
+        writer.write_u16(16)?;
+        writer.write_u32(32)?;
+        writer.write_u16(1616)?;
+        writer.write_u32(3232)?;
+
+        input.set_position(0);
+        let mut output = Cursor::new(Vec::new());
+        let mut reader = BinaryReader::new(&mut input, Endian::Big.into());
+        let mut writer =
+            BinaryWriter::new(&mut output, Endian::Little.into());
+        convert_endian(
+            &mut reader,
+            &mut writer,
+            &[FieldKind::U16, FieldKind::U32],
+        )?;
+
+        output.set_position(0);
+        let mut reader =
+            BinaryReader::new(&mut output, Endian::Little.into());
+        assert_eq!(16, reader.read_u16()?);
+        assert_eq!(32, reader.read_u32()?);
+        assert_eq!(1616, reader.read_u16()?);
+        assert_eq!(3232, reader.read_u32()?);
+        assert_eq!(12, reader.len()?);
+
+        Ok(())
+    }
+}